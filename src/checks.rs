@@ -1,12 +1,17 @@
+use std::ops::RangeInclusive;
+
 use serenity::{
     client::Context,
     framework::standard::{macros::check, Args, CommandOptions, Reason},
     model::prelude::*,
     prelude::Mentionable,
 };
-use tracing::{span, Instrument, Level};
+use tracing::{instrument, span, Instrument, Level};
 
-use crate::utils::SunnyError;
+use crate::{
+    effects::{config, get_requester, queue::RemoveFilter},
+    utils::{SunnyError, SunnyResult},
+};
 
 #[check]
 #[name = "In_Voice"]
@@ -20,48 +25,249 @@ pub async fn in_same_voice_check(
 ) -> Result<(), Reason> {
     let span = span!(Level::INFO, "in_same_voice_check", ?msg);
     async move {
-        let songbird = songbird::get(ctx)
-            .await
-            .ok_or_else(|| SunnyError::log("Failed to get songbird"))?;
+        let guild_id = msg
+            .guild_id
+            .ok_or_else(|| SunnyError::log("Guild ID Empty"))?;
+
+        in_same_voice(ctx, guild_id, msg.author.id).await?;
+        Ok(())
+    }
+    .instrument(span)
+    .await
+}
+
+#[check]
+#[name = "Manage_Guild"]
+#[display_in_help]
+// Ensures a command is only usable by members who can manage the server
+pub async fn manage_guild_check(
+    ctx: &Context,
+    msg: &Message,
+    _args: &mut Args,
+    _command_options: &CommandOptions,
+) -> Result<(), Reason> {
+    let span = span!(Level::INFO, "manage_guild_check", ?msg);
+    async move {
+        let guild_id = msg
+            .guild_id
+            .ok_or_else(|| SunnyError::log("Guild ID Empty"))?;
+
+        has_permission(ctx, guild_id, msg.author.id, Permissions::MANAGE_GUILD).await?;
+        Ok(())
+    }
+    .instrument(span)
+    .await
+}
+
+async fn member_permissions(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> SunnyResult<Permissions> {
+    let guild = ctx
+        .cache
+        .guild(guild_id)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get guild"))?;
+
+    guild
+        .member_permissions(ctx, user_id)
+        .await
+        .map_err(|e| SunnyError::log(format!("Couldn't get permissions: {}", e).as_str()))
+}
+
+/// Checks whether `user_id` has `permission` in the guild, shared by the
+/// permission checks and the slash commands.
+#[instrument(skip(ctx))]
+pub async fn has_permission(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    permission: Permissions,
+) -> SunnyResult<()> {
+    if !member_permissions(ctx, guild_id, user_id)
+        .await?
+        .contains(permission)
+    {
+        return Err(SunnyError::user(
+            format!(
+                "You need the {} permission for that",
+                permission.get_permission_names().join(", ")
+            )
+            .as_str(),
+        ));
+    }
+
+    Ok(())
+}
 
+#[check]
+#[name = "DJ"]
+#[display_in_help]
+// Ensures a command is only usable by DJs or moderators, once a guild configured a DJ role
+pub async fn dj_check(
+    ctx: &Context,
+    msg: &Message,
+    _args: &mut Args,
+    _command_options: &CommandOptions,
+) -> Result<(), Reason> {
+    let span = span!(Level::INFO, "dj_check", ?msg);
+    async move {
         let guild_id = msg
             .guild_id
             .ok_or_else(|| SunnyError::log("Guild ID Empty"))?;
 
-        let channel = {
-            let songbird_call_m = songbird
-                .get(guild_id)
-                .ok_or_else(|| SunnyError::user("Not currently in a call"))?;
-
-            let songbird_call = songbird_call_m.lock().await;
-
-            songbird_call
-                .current_channel()
-                .ok_or_else(|| SunnyError::log("Couldn't find songbird channel"))?
-        };
-
-        let name = ChannelId(channel.0);
-
-        let guild = msg
-            .guild(&ctx.cache)
-            .await
-            .ok_or_else(|| SunnyError::log("Couldn't get guild"))?;
-
-        let mut states = guild.voice_states.values();
-
-        states
-            .any(|vs| match vs.channel_id {
-                Some(c_id) => channel.0 == c_id.0 && vs.user_id.0 == msg.author.id.0,
-                None => false,
-            })
-            .then(|| ())
-            .ok_or_else(|| {
-                SunnyError::user(
-                    format!("I only take requests from users in {}", name.mention()).as_str(),
-                )
-            })?;
+        is_dj(ctx, guild_id, msg.author.id).await?;
         Ok(())
     }
     .instrument(span)
     .await
 }
+
+/// Checks whether `user_id` may use the DJ commands, shared by the `DJ` check
+/// and the slash commands.
+///
+/// Without a DJ role everyone passes, otherwise only members with the role and
+/// members who can manage channels do.
+#[instrument(skip(ctx))]
+pub async fn is_dj(ctx: &Context, guild_id: GuildId, user_id: UserId) -> SunnyResult<()> {
+    let role_id = match config::get_config(guild_id).await.dj_role {
+        Some(role_id) => role_id,
+        None => return Ok(()),
+    };
+
+    if has_dj_role(ctx, guild_id, user_id).await?
+        || member_permissions(ctx, guild_id, user_id)
+            .await?
+            .manage_channels()
+    {
+        return Ok(());
+    }
+
+    Err(SunnyError::user(
+        format!(
+            "Only the {} role or moderators can do that",
+            role_id.mention()
+        )
+        .as_str(),
+    ))
+}
+
+/// Checks whether `user_id` may change the songs at `positions` of the queue.
+///
+/// DJs may change any song, everyone else only the songs they queued themselves.
+#[instrument(skip(ctx))]
+pub async fn is_dj_or_requester(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    positions: RangeInclusive<usize>,
+) -> SunnyResult<()> {
+    let dj = is_dj(ctx, guild_id, user_id).await;
+
+    if dj.is_ok() {
+        return dj;
+    }
+
+    let queue = match songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Failed to get songbird"))?
+        .get(guild_id)
+    {
+        Some(call_m) => call_m.lock().await.queue().current_queue(),
+        None => return dj,
+    };
+
+    // Positions past the end of the queue are left for the command to complain about
+    for track in queue
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| positions.contains(i))
+        .map(|(_, t)| t)
+    {
+        if get_requester(track).await.map(|r| r.user_id) != Some(user_id) {
+            return Err(SunnyError::user(
+                "You can only change songs you queued yourself, DJs and moderators can change any",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether `user_id` may remove the songs `filter` matches
+#[instrument(skip(ctx))]
+pub async fn can_remove(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    filter: RemoveFilter,
+) -> SunnyResult<()> {
+    match filter {
+        RemoveFilter::Range(from, to) => {
+            is_dj_or_requester(ctx, guild_id, user_id, from..=to).await
+        }
+        RemoveFilter::User(requester) if requester == user_id => Ok(()),
+        _ => is_dj(ctx, guild_id, user_id).await,
+    }
+}
+
+/// Whether `user_id` has the DJ role configured for the guild
+#[instrument(skip(ctx))]
+pub async fn has_dj_role(ctx: &Context, guild_id: GuildId, user_id: UserId) -> SunnyResult<bool> {
+    let role_id = match config::get_config(guild_id).await.dj_role {
+        Some(role_id) => role_id,
+        None => return Ok(false),
+    };
+
+    let member = guild_id
+        .member(ctx, user_id)
+        .await
+        .map_err(|e| SunnyError::log(format!("Couldn't get member: {}", e).as_str()))?;
+
+    Ok(member.roles.contains(&role_id))
+}
+
+/// Checks whether `user_id` is in the same voice channel as sunny, shared by
+/// the `In_Voice` check and the slash commands.
+#[instrument(skip(ctx))]
+pub async fn in_same_voice(ctx: &Context, guild_id: GuildId, user_id: UserId) -> SunnyResult<()> {
+    let songbird = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Failed to get songbird"))?;
+
+    let channel = {
+        let songbird_call_m = songbird
+            .get(guild_id)
+            .ok_or_else(|| SunnyError::user("Not currently in a call"))?;
+
+        let songbird_call = songbird_call_m.lock().await;
+
+        songbird_call
+            .current_channel()
+            .ok_or_else(|| SunnyError::log("Couldn't find songbird channel"))?
+    };
+
+    let name = ChannelId(channel.0);
+
+    let guild = ctx
+        .cache
+        .guild(guild_id)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get guild"))?;
+
+    let mut states = guild.voice_states.values();
+
+    let in_voice = states.any(|vs| match vs.channel_id {
+        Some(c_id) => channel.0 == c_id.0 && vs.user_id.0 == user_id.0,
+        None => false,
+    });
+
+    if !in_voice {
+        return Err(SunnyError::user(
+            format!("I only take requests from users in {}", name.mention()).as_str(),
+        ));
+    }
+
+    Ok(())
+}
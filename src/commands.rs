@@ -1,4 +1,4 @@
-use std::{collections::HashSet, num::NonZeroUsize};
+use std::{collections::HashSet, num::NonZeroUsize, time::Duration};
 
 use serenity::{
     client::Context,
@@ -7,19 +7,25 @@ use serenity::{
         macros::{command, help},
         Args, CommandGroup, CommandResult, HelpOptions,
     },
+    http::AttachmentType,
     model::prelude::*,
 };
-
-use url::Url;
+use songbird::tracks::TrackHandle;
 
 use crate::{
     checks::*,
     effects::{
-        self, display_queue, now_playing,
-        queue::{self, EnqueueAt},
+        self,
+        config::{self, ConfigKey},
+        display_queue, history, now_playing,
+        playlists::{self, PlaylistScope, SavedPlaylist},
+        queue::{self, EnqueueAt, LoopMode, PlaylistSummary, RemoveFilter, SeekTarget, SkipVote},
+        search,
+        transfer::{self, Format},
     },
+    sources::{self, Filter, MAX_PLAYLIST_ENTRIES},
     structs::EventConfig,
-    utils::SunnyError,
+    utils::{SunnyError, SunnyResult},
 };
 
 #[help]
@@ -54,11 +60,8 @@ pub async fn join(ctx: &Context, msg: &Message) -> CommandResult {
         .ok_or_else(|| SunnyError::user("Not in a voice"))?;
 
     let bot_id = ctx.cache.current_user_id().await;
-    let same_voice = guild
-        .voice_states
-        .get(&bot_id)
-        .and_then(|vs| vs.channel_id)
-        .map_or(false, |id| id == voice_channel_id);
+    let same_voice =
+        guild.voice_states.get(&bot_id).and_then(|vs| vs.channel_id) == Some(voice_channel_id);
 
     if same_voice {
         return Err(SunnyError::user("Already in that voice channel!").into());
@@ -82,7 +85,7 @@ pub async fn join(ctx: &Context, msg: &Message) -> CommandResult {
 
 #[command]
 #[only_in(guilds)]
-#[checks(In_Voice)]
+#[checks(In_Voice, DJ)]
 /// Removes Sunny from the current voice channel and clears the queue.
 pub async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
     let guild = msg
@@ -97,40 +100,83 @@ pub async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
-fn validate_url(mut args: Args) -> Option<String> {
-    let mut url: String = args.single().ok()?;
+/// Takes `song` as either a url or search terms for the first YouTube result
+pub fn parse_song(song: &str) -> SunnyResult<String> {
+    let song = song.trim();
+
+    if song.is_empty() {
+        return Err(SunnyError::user("Give me a url or something to search for"));
+    }
+
+    // Paths can have spaces, which urls can't
+    if song.starts_with(sources::LOCAL_PREFIX) {
+        return Ok(song.to_string());
+    }
+
+    Ok(sources::parse_url(song).unwrap_or_else(|| sources::first_result(song)))
+}
+
+pub fn playlist_reply(summary: &PlaylistSummary) -> String {
+    let mut reply = format!("Added {} songs", summary.added);
 
-    if url.starts_with('<') && url.ends_with('>') {
-        url = url[1..url.len() - 1].to_string();
+    // Imported songs are only looked up once they play
+    if !summary.duration.is_zero() {
+        reply.push_str(&format!(
+            " ({})",
+            effects::format_total_duration(summary.duration)
+        ));
     }
 
-    Url::parse(&url).ok()?;
+    if summary.truncated {
+        reply.push_str(&format!(
+            ", playlists are capped at {} songs",
+            *MAX_PLAYLIST_ENTRIES
+        ));
+    }
+
+    reply
+}
 
-    Some(url)
+/// Whether `attachment` is a file Sunny can play
+fn is_audio(attachment: &&Attachment) -> bool {
+    matches!(&attachment.content_type, Some(t) if t.starts_with("audio/"))
 }
 
 #[command]
 #[aliases(p)]
-#[max_args(1)]
 #[only_in(guilds)]
-#[usage("<url>")]
+#[usage("<url | search terms | local:path>")]
 #[example("https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
+#[example("never gonna give you up")]
+#[example("local:albums/track01.flac")]
 #[checks(In_Voice)]
 /// While Sunny is in a voice channel, you may run the play command so that she
-/// can start streaming the given video URL.
+/// can start streaming the given video URL. Playlists add every song in them,
+/// anything else is looked up on YouTube. Without anything to play, an
+/// attached audio file is played instead.
 pub async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let url = validate_url(args).ok_or_else(|| SunnyError::user("Unable to parse url"))?;
+    let url = match msg.attachments.iter().find(is_audio) {
+        Some(attachment) if args.rest().trim().is_empty() => attachment.url.clone(),
+        _ => parse_song(args.rest())?,
+    };
 
     let guild_id = msg
         .guild_id
         .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
 
-    let len = queue::play(ctx, guild_id, url, EnqueueAt::Back).await?;
+    if sources::is_playlist(&url) {
+        let summary =
+            queue::play_playlist(ctx, guild_id, url, Some(msg.author.id), EnqueueAt::Back).await?;
+        msg.reply(&ctx.http, playlist_reply(&summary)).await?;
+        return Ok(());
+    }
+
+    let position = queue::play(ctx, guild_id, url, Some(msg.author.id), EnqueueAt::Back).await?;
 
-    let reply = if len == 1 {
+    let reply = if position == 0 {
         "Started playing the song".to_string()
     } else {
-        format!("Added song to queue: position {}", len - 1)
+        format!("Added song to queue: position {}", position)
     };
 
     msg.reply(&ctx.http, reply).await?;
@@ -138,23 +184,68 @@ pub async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     Ok(())
 }
 
+/// Adds the radio station at `url` to the queue, shared by both command front ends
+pub async fn radio_reply(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    url: &str,
+) -> SunnyResult<String> {
+    let url = sources::parse_url(url.trim())
+        .ok_or_else(|| SunnyError::user("Give me the url of a radio stream"))?;
+
+    let url = format!("{}{}", sources::RADIO_PREFIX, url);
+    let position = queue::play(ctx, guild_id, url, Some(user_id), EnqueueAt::Back).await?;
+
+    Ok(if position == 0 {
+        "Tuned in to the station".to_string()
+    } else {
+        format!("Added the station to queue: position {}", position)
+    })
+}
+
+#[command]
+#[only_in(guilds)]
+#[usage("<url>")]
+#[example("https://ice1.somafm.com/groovesalad-128-mp3")]
+#[checks(In_Voice)]
+/// Plays a live internet radio stream, like an Icecast or Shoutcast station
+pub async fn radio(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let reply = radio_reply(ctx, guild_id, msg.author.id, args.rest()).await?;
+
+    msg.reply(&ctx.http, reply).await?;
+
+    Ok(())
+}
+
 #[command]
 #[aliases(pn)]
-#[max_args(1)]
 #[only_in(guilds)]
-#[usage("<url>")]
+#[usage("<url | search terms>")]
 #[example("https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
+#[example("never gonna give you up")]
 #[checks(In_Voice)]
 /// While Sunny is in a voice channel, you may run the play command so that she
-/// can start streaming the given video URL.
+/// can start streaming the given video URL or the first search result.
 pub async fn play_next(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let url = validate_url(args).ok_or_else(|| SunnyError::user("Unable to parse url"))?;
+    let url = parse_song(args.rest())?;
 
     let guild_id = msg
         .guild_id
         .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
 
-    queue::play(ctx, guild_id, url, EnqueueAt::Front).await?;
+    if sources::is_playlist(&url) {
+        let summary =
+            queue::play_playlist(ctx, guild_id, url, Some(msg.author.id), EnqueueAt::Front).await?;
+        msg.reply(&ctx.http, playlist_reply(&summary)).await?;
+        return Ok(());
+    }
+
+    queue::play(ctx, guild_id, url, Some(msg.author.id), EnqueueAt::Front).await?;
 
     msg.reply(&ctx.http, "Added song to front of queue").await?;
 
@@ -163,6 +254,7 @@ pub async fn play_next(ctx: &Context, msg: &Message, args: Args) -> CommandResul
 
 #[command]
 #[only_in(guilds)]
+#[checks(In_Voice, DJ)]
 /// Shuffles your queue badly
 pub async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
     let guild_id = msg
@@ -179,6 +271,7 @@ pub async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
 #[min_args(2)]
 #[max_args(2)]
 #[usage("<position> <position>")]
+#[checks(In_Voice)]
 #[example("4 2")]
 /// Swaps two songs in the queue by their number
 pub async fn swap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
@@ -194,6 +287,9 @@ pub async fn swap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
         .single::<NonZeroUsize>()
         .map_err(|_| SunnyError::user("Invalid arguments"))?;
 
+    is_dj_or_requester(ctx, guild_id, msg.author.id, a.get()..=a.get()).await?;
+    is_dj_or_requester(ctx, guild_id, msg.author.id, b.get()..=b.get()).await?;
+
     let (t1, t2) = queue::swap(ctx, guild_id, a.into(), b.into()).await?;
 
     msg.reply(
@@ -209,6 +305,87 @@ pub async fn swap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
     Ok(())
 }
 
+#[command("move")]
+#[only_in(guilds)]
+#[aliases(mv)]
+#[min_args(2)]
+#[max_args(2)]
+#[usage("<from> <to>")]
+#[example("14 2")]
+#[checks(In_Voice)]
+/// Moves a song to another position in the queue, shifting the songs in between
+pub async fn move_track(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("Failed to get guild id"))?;
+
+    let from = args
+        .single::<NonZeroUsize>()
+        .map_err(|_| SunnyError::user("Invalid arguments"))?;
+
+    let to = args
+        .single::<NonZeroUsize>()
+        .map_err(|_| SunnyError::user("Invalid arguments"))?;
+
+    is_dj_or_requester(ctx, guild_id, msg.author.id, from.get()..=from.get()).await?;
+
+    let track = queue::move_track(ctx, guild_id, from.into(), to.into()).await?;
+
+    msg.reply(
+        &ctx.http,
+        format!(
+            "Moved `{}` to position {}",
+            effects::get_song(track.metadata()),
+            to
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[command("loop")]
+#[only_in(guilds)]
+#[aliases(repeat)]
+#[checks(In_Voice)]
+/// Keeps repeating the current song, run it again to stop looping
+pub async fn loop_track(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let mode = queue::toggle_loop(ctx, guild_id, LoopMode::Track).await?;
+
+    msg.reply(&ctx.http, loop_reply(mode)).await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(lq, repeat_queue)]
+#[checks(In_Voice)]
+/// Keeps repeating the whole queue, run it again to stop looping
+pub async fn loop_queue(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let mode = queue::toggle_loop(ctx, guild_id, LoopMode::Queue).await?;
+
+    msg.reply(&ctx.http, loop_reply(mode)).await?;
+
+    Ok(())
+}
+
+pub fn loop_reply(mode: LoopMode) -> String {
+    match mode {
+        LoopMode::Off => "Stopped looping".to_string(),
+        LoopMode::Track => format!("Looping the current song {}", mode),
+        LoopMode::Queue => format!("Looping the queue {}", mode),
+    }
+}
+
 #[command]
 #[only_in(guilds)]
 #[aliases(np)]
@@ -258,30 +435,206 @@ pub async fn resume(ctx: &Context, msg: &Message) -> CommandResult {
 }
 
 #[command]
+#[aliases(vol)]
 #[only_in(guilds)]
+#[max_args(1)]
+#[usage("[0-200]")]
+#[example("50")]
 #[checks(In_Voice)]
-/// Skips the currently playing song and starts the next song in the queue.
-pub async fn skip(ctx: &Context, msg: &Message) -> CommandResult {
+/// Sets the volume in percent, new songs keep playing at that volume.
+/// Without a number it shows the current volume.
+pub async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    if args.is_empty() {
+        let volume = config::get_config(guild_id).await.volume;
+        msg.reply(&ctx.http, format!("The volume is {}%", volume))
+            .await?;
+        return Ok(());
+    }
+
+    let volume = args
+        .single::<u16>()
+        .map_err(|_| SunnyError::user("The volume has to be a number from 0 to 200"))?;
+
+    queue::set_volume(ctx, guild_id, volume).await?;
+
+    msg.reply(&ctx.http, format!("Set the volume to {}%", volume))
+        .await?;
+
+    Ok(())
+}
+
+/// Shows the filter without a `preset`, otherwise applies it. Shared by both command front ends
+pub async fn filter_reply(
+    ctx: &Context,
+    guild_id: GuildId,
+    preset: Option<&str>,
+) -> SunnyResult<String> {
+    let filter = match preset {
+        Some(preset) => preset.parse::<Filter>()?,
+        None => {
+            let filter = sources::get_filter(guild_id).await;
+            let presets = Filter::ALL
+                .iter()
+                .map(|f| format!("`{}`", f))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return Ok(format!(
+                "The filter is `{}`, the presets are {}",
+                filter, presets
+            ));
+        }
+    };
+
+    queue::set_filter(ctx, guild_id, filter).await?;
+
+    Ok(if filter == Filter::Off {
+        "Turned the filter off".to_string()
+    } else {
+        format!("Turned on the `{}` filter", filter)
+    })
+}
+
+#[command]
+#[aliases(fx)]
+#[only_in(guilds)]
+#[max_args(1)]
+#[usage("[off | bassboost | nightcore | vaporwave | 8d | normalize]")]
+#[example("nightcore")]
+#[checks(In_Voice)]
+/// Applies an audio filter to the current and later songs, `off` turns it off again.
+/// Without a preset it shows the current filter.
+pub async fn filter(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let guild_id = msg
         .guild_id
         .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
 
-    let len = queue::skip(ctx, guild_id).await?;
+    let preset = Some(args.rest()).filter(|p| !p.trim().is_empty());
+    let reply = filter_reply(ctx, guild_id, preset).await?;
+
+    msg.reply(&ctx.http, reply).await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[usage("<mm:ss | hh:mm:ss | +30s | -1m>")]
+#[example("1:30")]
+#[example("+30s")]
+#[checks(In_Voice)]
+/// Jumps to a timestamp in the current song, or moves relative to the current position
+pub async fn seek(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let target = args.single::<String>()?.parse::<SeekTarget>()?;
+
+    let position = queue::seek(ctx, guild_id, target).await?;
 
     msg.reply(
         &ctx.http,
-        format!(
-            "Song skipped: {} in queue.",
-            len.checked_sub(1).unwrap_or_default()
-        ),
+        format!("Seeked to {}", effects::format_duration(position)),
     )
     .await?;
+
     Ok(())
 }
 
+/// How far `forward` and `rewind` move without an argument
+pub const DEFAULT_SEEK_STEP: Duration = Duration::from_secs(10);
+
 #[command]
 #[only_in(guilds)]
+#[aliases(ff)]
+#[max_args(1)]
+#[usage("[amount]")]
+#[example("30s")]
 #[checks(In_Voice)]
+/// Skips ahead in the current song, 10 seconds by default
+pub async fn forward(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let amount = parse_seek_step(args)?;
+
+    let position = queue::seek(ctx, guild_id, SeekTarget::Forward(amount)).await?;
+
+    msg.reply(
+        &ctx.http,
+        format!("Seeked to {}", effects::format_duration(position)),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(rw)]
+#[max_args(1)]
+#[usage("[amount]")]
+#[example("1m")]
+#[checks(In_Voice)]
+/// Goes back in the current song, 10 seconds by default
+pub async fn rewind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let amount = parse_seek_step(args)?;
+
+    let position = queue::seek(ctx, guild_id, SeekTarget::Backward(amount)).await?;
+
+    msg.reply(
+        &ctx.http,
+        format!("Seeked to {}", effects::format_duration(position)),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn parse_seek_step(mut args: Args) -> SunnyResult<Duration> {
+    match args.single::<String>() {
+        Ok(amount) => queue::parse_duration(&amount),
+        Err(_) => Ok(DEFAULT_SEEK_STEP),
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(In_Voice)]
+/// Skips the currently playing song and starts the next song in the queue.
+/// With vote skipping on, enough listeners have to vote for it first.
+pub async fn skip(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let vote = queue::vote_skip(ctx, guild_id, msg.author.id).await?;
+
+    msg.reply(&ctx.http, skip_reply(&vote)).await?;
+    Ok(())
+}
+
+pub fn skip_reply(vote: &SkipVote) -> String {
+    match vote {
+        SkipVote::Skipped(len) => format!("Song skipped: {} in queue.", len.saturating_sub(1)),
+        SkipVote::Voted { votes, needed } => format!("{}/{} votes to skip", votes, needed),
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(In_Voice, DJ)]
 /// Stops playing the current song and clears the current song queue.
 pub async fn stop(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
     let guild_id = msg
@@ -308,30 +661,442 @@ pub async fn queue(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
+/// Runs a `config` action, shared by both command front ends
+pub async fn config_reply(
+    guild_id: GuildId,
+    action: &str,
+    key: Option<&str>,
+    value: &str,
+) -> SunnyResult<String> {
+    let key = key.map(str::parse::<ConfigKey>).transpose()?;
+
+    match (action, key) {
+        ("get", None) => {
+            let config = config::get_config(guild_id).await;
+
+            Ok(ConfigKey::ALL
+                .iter()
+                .map(|k| format!("**{}:** {}", k, config.get(*k)))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        ("get", Some(key)) => Ok(format!(
+            "**{}:** {}",
+            key,
+            config::get_config(guild_id).await.get(key)
+        )),
+        ("set", Some(key)) => {
+            let value = config::set_config(guild_id, key, value).await?;
+            Ok(format!("Set {} to {}", key, value))
+        }
+        ("reset", Some(key)) => {
+            config::reset_config(guild_id, Some(key)).await?;
+            Ok(format!("Reset {}", key))
+        }
+        ("reset", None) => {
+            config::reset_config(guild_id, None).await?;
+            Ok("Reset every setting".to_string())
+        }
+        _ => Err(SunnyError::user(
+            "Try `config get [setting]`, `config set <setting> <value>` or `config reset [setting]`",
+        )),
+    }
+}
+
 #[command]
 #[only_in(guilds)]
-#[aliases(r, remove)]
-#[max_args(1)]
-#[example("2")]
-#[usage("<position>")]
-/// Removes a song from the queue by its position
-pub async fn remove_at(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+#[usage("[get [setting] | set <setting> <value> | reset [setting]]")]
+#[example("set prefix !")]
+#[example("set announce_channel #music")]
+#[example("set max_song_duration 15m")]
+#[example("reset volume")]
+#[checks(Manage_Guild)]
+/// Shows or changes the settings of this server: `prefix`, `idle_timeout`,
+/// `volume`, `announce_channel`, `max_queue_length`, `max_user_songs`,
+/// `max_song_duration`, `now_playing`, `vote_skip`, `vote_skip_ratio`,
+/// `dj_role`, `fair_queue`, `autoplay`, `normalize` and `crossfade`.
+pub async fn config(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let guild_id = msg
         .guild_id
         .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
 
-    #[allow(clippy::unwrap_used)]
-    let index = args
-        .single::<NonZeroUsize>()
-        .unwrap_or_else(|_| NonZeroUsize::new(1).unwrap());
+    let action = args
+        .single::<String>()
+        .unwrap_or_else(|_| "get".to_string());
+    let key = args.single::<String>().ok();
+
+    let reply = config_reply(guild_id, &action, key.as_deref(), args.rest()).await?;
+
+    msg.reply(&ctx.http, reply).await?;
+
+    Ok(())
+}
+
+/// Songs of a saved playlist listed by `playlist show`
+const PLAYLIST_SONGS_SHOWN: usize = 15;
+/// Most characters Discord allows in a message
+const MESSAGE_LIMIT: usize = 2000;
+/// Room kept free for the "and N more" line
+const MORE_LINE_LENGTH: usize = 32;
+
+fn saved_playlist_reply(playlist: &SavedPlaylist) -> String {
+    let mut reply = format!(
+        "**{}**: {} songs ({}), saved by {} <t:{}:R>",
+        playlist.name,
+        playlist.tracks.len(),
+        effects::format_total_duration(playlist.duration()),
+        playlist.saved_by.mention(),
+        playlist.saved_at.timestamp()
+    );
+    let mut length = reply.chars().count();
+    let mut shown = 0;
+
+    // Long titles could push the reply past the message limit before all songs are listed
+    for (i, t) in playlist
+        .tracks
+        .iter()
+        .take(PLAYLIST_SONGS_SHOWN)
+        .enumerate()
+    {
+        let line = format!(
+            "\n**{}.** {} [{}]",
+            i + 1,
+            effects::get_song(&t.clone().into()),
+            effects::format_duration(t.duration.unwrap_or_default())
+        );
+        let line_length = line.chars().count();
+
+        if length + line_length + MORE_LINE_LENGTH > MESSAGE_LIMIT {
+            break;
+        }
+
+        reply.push_str(&line);
+        length += line_length;
+        shown += 1;
+    }
+
+    if playlist.tracks.len() > shown {
+        reply.push_str(&format!("\nand {} more", playlist.tracks.len() - shown));
+    }
+
+    reply
+}
+
+/// Runs a `playlist` action, shared by both command front ends.
+///
+/// Playlists belong to `user_id`, or the whole server with `server`.
+pub async fn playlists_reply(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    action: &str,
+    server: bool,
+    name: Option<&str>,
+) -> SunnyResult<String> {
+    let scope = if server {
+        PlaylistScope::Guild(guild_id)
+    } else {
+        PlaylistScope::User(user_id)
+    };
+
+    match (action, name) {
+        ("list", _) => {
+            let names = playlists::list(scope).await?;
+
+            if names.is_empty() {
+                Ok("No saved playlists yet".to_string())
+            } else {
+                Ok(format!("Saved playlists: `{}`", names.join("`, `")))
+            }
+        }
+        ("show", Some(name)) => Ok(saved_playlist_reply(
+            &playlists::get_playlist(scope, name).await?,
+        )),
+        ("load", Some(name)) => {
+            in_same_voice(ctx, guild_id, user_id).await?;
+
+            let summary = playlists::load(ctx, guild_id, scope, name, user_id).await?;
+            Ok(playlist_reply(&summary))
+        }
+        ("save", Some(name)) => {
+            if server {
+                is_dj(ctx, guild_id, user_id).await?;
+            }
+
+            let playlist = playlists::save(ctx, guild_id, scope, name, user_id).await?;
+            Ok(format!(
+                "Saved {} songs as `{}`",
+                playlist.tracks.len(),
+                playlist.name
+            ))
+        }
+        ("delete", Some(name)) => {
+            if server {
+                is_dj(ctx, guild_id, user_id).await?;
+            }
+
+            playlists::delete(scope, name).await?;
+            Ok(format!("Deleted `{}`", name.trim().to_lowercase()))
+        }
+        _ => Err(SunnyError::user(
+            "Try `playlist list`, or `playlist save`, `load`, `show` or `delete` with a name",
+        )),
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(pl)]
+#[usage("<list | save | load | show | delete> [server] [name]")]
+#[example("save weekly")]
+#[example("load server friday-set")]
+#[example("list server")]
+/// Saves the queue under a name to play it again later. Playlists are your own,
+/// unless `server` comes before the name to share them with the whole server.
+pub async fn playlist(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let action = args
+        .single::<String>()
+        .unwrap_or_else(|_| "list".to_string());
+
+    let mut name = args.single::<String>().ok();
+    let server = name.as_deref() == Some("server");
+
+    if server {
+        name = args.single::<String>().ok();
+    }
+
+    let reply = playlists_reply(
+        ctx,
+        guild_id,
+        msg.author.id,
+        &action,
+        server,
+        name.as_deref(),
+    )
+    .await?;
+
+    msg.reply(&ctx.http, reply).await?;
+
+    Ok(())
+}
+
+/// Names the file the queue is exported to
+pub fn export_filename(format: Format) -> String {
+    format!("queue.{}", format.extension())
+}
+
+#[command]
+#[only_in(guilds)]
+#[usage("[m3u | json]")]
+#[example("json")]
+/// Sends the queue as an `.m3u` playlist or `.json` file, which `import` takes again
+pub async fn export(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let format = match args.single::<String>() {
+        Ok(format) => format.parse()?,
+        Err(_) => Format::M3u,
+    };
+
+    let (file, count) = transfer::export(ctx, guild_id, format).await?;
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.reference_message(msg);
+            m.content(format!("Exported {} songs", count));
+            m.add_file(AttachmentType::Bytes {
+                data: file.into(),
+                filename: export_filename(format),
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Imports the queue file `attachment`, shared by both command front ends
+pub async fn import_reply(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    attachment: &Attachment,
+) -> SunnyResult<String> {
+    let format = Format::from_filename(&attachment.filename)?;
+
+    if attachment.size > transfer::MAX_FILE_SIZE {
+        return Err(SunnyError::user("That file is too big to be a queue"));
+    }
+
+    let file = attachment.download().await.map_err(|e| {
+        SunnyError::user_and_log(
+            "Couldn't download that file",
+            format!("Failed to download attachment: {:?}", e).as_str(),
+        )
+    })?;
 
-    let q = queue::remove_at(ctx, guild_id, index).await?;
+    let summary = transfer::import(ctx, guild_id, format, &file, user_id).await?;
+
+    Ok(playlist_reply(&summary))
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(In_Voice)]
+/// Adds every song of an attached `.m3u` or `.json` file, like the ones `export` sends
+pub async fn import(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let attachment = msg
+        .attachments
+        .first()
+        .ok_or_else(|| SunnyError::user("Attach an `.m3u` or `.json` file to import"))?;
+
+    let reply = import_reply(ctx, guild_id, msg.author.id, attachment).await?;
+
+    msg.reply(&ctx.http, reply).await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(h, recent)]
+/// Shows the songs which played recently
+pub async fn history(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    history::send_embed(ctx, guild_id, msg.channel_id).await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(back, prev)]
+#[checks(In_Voice)]
+/// Plays the last played song again, right after the current one
+pub async fn previous(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let m = history::play_previous(ctx, guild_id).await?;
 
     msg.reply(
         &ctx.http,
-        format!("Removed: `{}`", effects::get_song(q.metadata())),
+        format!("Added {} to the front of the queue", effects::get_song(&m)),
     )
     .await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(1)]
+#[usage("<search terms>")]
+#[example("never gonna give you up")]
+#[checks(In_Voice)]
+/// Shows the top YouTube results for your search, pick one to add it to the queue
+pub async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    search::send_embed(ctx, guild_id, msg.channel_id, msg.author.id, args.rest()).await?;
+
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases(r, remove)]
+#[example("2")]
+#[example("3-9")]
+#[example("@user")]
+#[example("duplicates")]
+#[example("unavailable")]
+#[usage("<position | from-to | user | duplicates | unavailable>")]
+#[checks(In_Voice)]
+/// Removes a song from the queue by its position, or every song in a range,
+/// requested by a user, queued twice or no longer available
+pub async fn remove_at(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let songs = args.rest().trim();
+
+    // Without arguments the next song goes
+    let index = if songs.is_empty() {
+        NonZeroUsize::new(1)
+    } else {
+        songs.parse::<NonZeroUsize>().ok()
+    };
+
+    let reply = if let Some(index) = index {
+        is_dj_or_requester(ctx, guild_id, msg.author.id, index.get()..=index.get()).await?;
+
+        let q = queue::remove_at(ctx, guild_id, index).await?;
+
+        format!("Removed: `{}`", effects::get_song(q.metadata()))
+    } else {
+        let filter = songs.parse()?;
+        can_remove(ctx, guild_id, msg.author.id, filter).await?;
+
+        let removed = queue::remove(ctx, guild_id, filter).await?;
+
+        removed_reply(&removed)
+    };
+
+    msg.reply(&ctx.http, reply).await?;
+    Ok(())
+}
+
+/// Amount of removed songs listed by name
+const REMOVED_SHOWN: usize = 5;
+
+/// Sums up the songs taken out of the queue
+pub fn removed_reply(removed: &[TrackHandle]) -> String {
+    let mut names = removed
+        .iter()
+        .take(REMOVED_SHOWN)
+        .map(|t| format!("`{}`", effects::get_song(t.metadata())))
+        .collect::<Vec<_>>();
+
+    if removed.len() > REMOVED_SHOWN {
+        names.push(format!("and {} more", removed.len() - REMOVED_SHOWN));
+    }
+
+    format!(
+        "Removed {} song{}: {}",
+        removed.len(),
+        if removed.len() == 1 { "" } else { "s" },
+        names.join(", ")
+    )
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(In_Voice, DJ)]
+/// Removes every song from the queue but the current one
+pub async fn clear(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id = msg
+        .guild_id
+        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
+
+    let removed = queue::remove(ctx, guild_id, RemoveFilter::All).await?;
+
+    msg.reply(&ctx.http, removed_reply(&removed)).await?;
     Ok(())
 }
 
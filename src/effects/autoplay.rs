@@ -0,0 +1,82 @@
+//! # Autoplay
+//! Keeps the music going once the queue runs out, by playing a song related
+//! to the last one. Guilds opt in with `config set autoplay on`.
+
+use std::collections::HashSet;
+
+use serenity::{client::Context, model::id::GuildId};
+use songbird::tracks::{PlayMode, TrackHandle, TrackState};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    sources,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    config::get_config,
+    get_song,
+    history::get_history,
+    queue::{self, get_loop_mode, LoopMode},
+};
+
+/// Recently played songs which won't be picked again
+const RECENT_SONGS: usize = 20;
+
+/// Plays a song related to `ended` if the queue ran out with it
+#[instrument(skip(ctx, state, ended))]
+pub async fn autoplay(
+    ctx: &Context,
+    guild_id: GuildId,
+    state: &TrackState,
+    ended: &TrackHandle,
+) -> SunnyResult<()> {
+    // Stopped and skipped songs end too, but nobody wants music right after `stop`
+    if state.playing != PlayMode::End || get_loop_mode(guild_id).await != LoopMode::Off {
+        return Ok(());
+    }
+
+    if !get_config(guild_id).await.autoplay {
+        return Ok(());
+    }
+
+    let url = match &ended.metadata().source_url {
+        Some(url) => url.clone(),
+        None => return Ok(()),
+    };
+
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    // The ended song might not have been popped off the queue yet
+    if current_queue.iter().any(|t| t.uuid() != ended.uuid()) {
+        return Ok(());
+    }
+
+    let recent = get_history(guild_id)
+        .await
+        .into_iter()
+        .take(RECENT_SONGS)
+        .map(|entry| entry.track.source_url)
+        .chain(Some(url.clone()))
+        .collect::<HashSet<_>>();
+
+    let picked = sources::related(&url)
+        .await?
+        .into_iter()
+        .find(|m| !matches!(&m.source_url, Some(url) if recent.contains(url)))
+        .ok_or_else(|| SunnyError::log("No related songs left to autoplay"))?;
+
+    event!(Level::INFO, song = %get_song(&picked), "Autoplaying");
+
+    queue::play_autoplayed(ctx, guild_id, picked).await?;
+
+    Ok(())
+}
@@ -0,0 +1,446 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    env, fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    model::id::{ChannelId, GuildId, RoleId},
+    prelude::{Mentionable, Mutex},
+    utils::{parse_channel, parse_role},
+};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    emit, storage,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    crossfade::MAX_CROSSFADE,
+    format_total_duration,
+    queue::{parse_duration, MAX_VOLUME},
+};
+
+const CONFIG_DIR: &str = "config";
+
+/// The prefix of guilds which didn't set their own
+pub static CMD_PREFIX: Lazy<String> =
+    Lazy::new(|| env::var("CMD_PREFIX").expect("Environment variable CMD_PREFIX not found"));
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildConfig {
+    /// Overrides [`CMD_PREFIX`]
+    pub prefix: Option<String>,
+    /// Minutes Sunny stays in a voice channel without listeners
+    pub idle_timeout: u64,
+    /// Volume in percent new tracks start at
+    pub volume: u16,
+    /// Channel now playing embeds go to, instead of the one Sunny was summoned from
+    pub announce_channel: Option<ChannelId>,
+    pub max_queue_length: Option<usize>,
+    /// Songs a single member may have in the queue at once
+    pub max_user_songs: Option<usize>,
+    /// Longest song which may be queued
+    pub max_song_duration: Option<Duration>,
+    /// Whether to post a now playing embed whenever a new track starts
+    pub now_playing: bool,
+    /// Whether skipping takes votes of the listeners
+    pub vote_skip: bool,
+    /// Percentage of listeners needed to skip
+    pub vote_skip_ratio: u8,
+    /// Role of the members allowed to skip without voting
+    pub dj_role: Option<RoleId>,
+    /// Whether new songs are spread out so requesters take turns
+    pub fair_queue: bool,
+    /// Whether to play related songs once the queue runs out
+    pub autoplay: bool,
+    /// Whether every song's volume is adjusted so songs sound equally loud
+    pub normalize: bool,
+    /// How long songs fade into each other, zero for no fade
+    pub crossfade: Duration,
+}
+
+impl Default for GuildConfig {
+    fn default() -> Self {
+        Self {
+            prefix: None,
+            idle_timeout: 5,
+            volume: 100,
+            announce_channel: None,
+            max_queue_length: None,
+            max_user_songs: None,
+            max_song_duration: None,
+            now_playing: true,
+            vote_skip: false,
+            vote_skip_ratio: 50,
+            dj_role: None,
+            fair_queue: false,
+            autoplay: false,
+            normalize: false,
+            crossfade: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigKey {
+    Prefix,
+    IdleTimeout,
+    Volume,
+    AnnounceChannel,
+    MaxQueueLength,
+    MaxUserSongs,
+    MaxSongDuration,
+    NowPlaying,
+    VoteSkip,
+    VoteSkipRatio,
+    DjRole,
+    FairQueue,
+    Autoplay,
+    Normalize,
+    Crossfade,
+}
+
+impl ConfigKey {
+    pub const ALL: [Self; 15] = [
+        Self::Prefix,
+        Self::IdleTimeout,
+        Self::Volume,
+        Self::AnnounceChannel,
+        Self::MaxQueueLength,
+        Self::MaxUserSongs,
+        Self::MaxSongDuration,
+        Self::NowPlaying,
+        Self::VoteSkip,
+        Self::VoteSkipRatio,
+        Self::DjRole,
+        Self::FairQueue,
+        Self::Autoplay,
+        Self::Normalize,
+        Self::Crossfade,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Prefix => "prefix",
+            Self::IdleTimeout => "idle_timeout",
+            Self::Volume => "volume",
+            Self::AnnounceChannel => "announce_channel",
+            Self::MaxQueueLength => "max_queue_length",
+            Self::MaxUserSongs => "max_user_songs",
+            Self::MaxSongDuration => "max_song_duration",
+            Self::NowPlaying => "now_playing",
+            Self::VoteSkip => "vote_skip",
+            Self::VoteSkipRatio => "vote_skip_ratio",
+            Self::DjRole => "dj_role",
+            Self::FairQueue => "fair_queue",
+            Self::Autoplay => "autoplay",
+            Self::Normalize => "normalize",
+            Self::Crossfade => "crossfade",
+        }
+    }
+
+    /// What the value should look like, shown when it fails to parse
+    const fn hint(self) -> &'static str {
+        match self {
+            Self::Prefix => "any text without spaces",
+            Self::IdleTimeout => "a number of minutes",
+            Self::Volume => "a percentage between 0 and 200",
+            Self::AnnounceChannel => "a channel mention or `none`",
+            Self::MaxQueueLength | Self::MaxUserSongs => "a number of songs or `none`",
+            Self::MaxSongDuration => "a duration like `10m` or `1:30:00`, or `none`",
+            Self::NowPlaying
+            | Self::VoteSkip
+            | Self::FairQueue
+            | Self::Autoplay
+            | Self::Normalize => "`on` or `off`",
+            Self::VoteSkipRatio => "a percentage between 1 and 100",
+            Self::Crossfade => "a number of seconds up to 10, or `off`",
+            Self::DjRole => "a role mention or `none`",
+        }
+    }
+}
+
+impl fmt::Display for ConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ConfigKey {
+    type Err = SunnyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|k| k.name() == s.trim())
+            .ok_or_else(|| {
+                let names = Self::ALL.map(Self::name);
+
+                SunnyError::user(
+                    format!("Unknown setting, try one of `{}`", names.join("`, `")).as_str(),
+                )
+            })
+    }
+}
+
+fn parse_optional<T: FromStr>(value: &str) -> Option<Option<T>> {
+    if value == "none" {
+        Some(None)
+    } else {
+        value.parse().ok().map(Some)
+    }
+}
+
+fn toggle_name(value: bool) -> String {
+    if value { "on" } else { "off" }.to_string()
+}
+
+fn parse_toggle(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" | "yes" => Some(true),
+        "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+impl GuildConfig {
+    /// Shows the value of `key` the way it is set
+    pub fn get(&self, key: ConfigKey) -> String {
+        match key {
+            ConfigKey::Prefix => format!("`{}`", self.prefix.as_deref().unwrap_or(&CMD_PREFIX)),
+            ConfigKey::IdleTimeout => format!("{} minutes", self.idle_timeout),
+            ConfigKey::Volume => format!("{}%", self.volume),
+            ConfigKey::AnnounceChannel => self
+                .announce_channel
+                .map_or_else(|| "none".to_string(), |c| c.mention().to_string()),
+            ConfigKey::MaxQueueLength => self
+                .max_queue_length
+                .map_or_else(|| "none".to_string(), |l| format!("{} songs", l)),
+            ConfigKey::MaxUserSongs => self
+                .max_user_songs
+                .map_or_else(|| "none".to_string(), |l| format!("{} songs", l)),
+            ConfigKey::MaxSongDuration => self
+                .max_song_duration
+                .map_or_else(|| "none".to_string(), format_total_duration),
+            ConfigKey::NowPlaying => toggle_name(self.now_playing),
+            ConfigKey::VoteSkip => toggle_name(self.vote_skip),
+            ConfigKey::VoteSkipRatio => format!("{}%", self.vote_skip_ratio),
+            ConfigKey::DjRole => self
+                .dj_role
+                .map_or_else(|| "none".to_string(), |r| r.mention().to_string()),
+            ConfigKey::FairQueue => toggle_name(self.fair_queue),
+            ConfigKey::Autoplay => toggle_name(self.autoplay),
+            ConfigKey::Normalize => toggle_name(self.normalize),
+            ConfigKey::Crossfade if self.crossfade.is_zero() => "off".to_string(),
+            ConfigKey::Crossfade => format!("{}s", self.crossfade.as_secs()),
+        }
+    }
+
+    fn set(&mut self, key: ConfigKey, value: &str) -> SunnyResult<()> {
+        let value = value.trim();
+        let invalid =
+            || SunnyError::user(format!("Invalid {}, expected {}", key, key.hint()).as_str());
+
+        match key {
+            ConfigKey::Prefix if !value.is_empty() && !value.contains(char::is_whitespace) => {
+                self.prefix = Some(value.to_string());
+            }
+            ConfigKey::IdleTimeout => {
+                self.idle_timeout = value.parse().map_err(|_| invalid())?;
+            }
+            ConfigKey::Volume => {
+                self.volume = value
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()
+                    .filter(|v| *v <= MAX_VOLUME)
+                    .ok_or_else(invalid)?;
+            }
+            ConfigKey::AnnounceChannel => {
+                self.announce_channel = if value == "none" {
+                    None
+                } else {
+                    Some(ChannelId(
+                        parse_channel(value)
+                            .or_else(|| value.parse().ok())
+                            .ok_or_else(invalid)?,
+                    ))
+                };
+            }
+            ConfigKey::MaxQueueLength => {
+                self.max_queue_length = parse_optional(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::MaxUserSongs => {
+                self.max_user_songs = parse_optional(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::MaxSongDuration => {
+                self.max_song_duration = if value == "none" {
+                    None
+                } else {
+                    Some(parse_duration(value).map_err(|_| invalid())?)
+                };
+            }
+            ConfigKey::NowPlaying => {
+                self.now_playing = parse_toggle(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::VoteSkip => {
+                self.vote_skip = parse_toggle(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::VoteSkipRatio => {
+                self.vote_skip_ratio = value
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()
+                    .filter(|r| (1..=100).contains(r))
+                    .ok_or_else(invalid)?;
+            }
+            ConfigKey::DjRole => {
+                self.dj_role = if value == "none" {
+                    None
+                } else {
+                    Some(RoleId(
+                        parse_role(value)
+                            .or_else(|| value.parse().ok())
+                            .ok_or_else(invalid)?,
+                    ))
+                };
+            }
+            ConfigKey::FairQueue => {
+                self.fair_queue = parse_toggle(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::Autoplay => {
+                self.autoplay = parse_toggle(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::Normalize => {
+                self.normalize = parse_toggle(value).ok_or_else(invalid)?;
+            }
+            ConfigKey::Crossfade => {
+                self.crossfade = if value == "off" {
+                    Duration::ZERO
+                } else {
+                    parse_duration(value)
+                        .ok()
+                        .filter(|d| *d <= MAX_CROSSFADE)
+                        .ok_or_else(invalid)?
+                };
+            }
+            ConfigKey::Prefix => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+
+    fn reset(&mut self, key: ConfigKey) {
+        let default = Self::default();
+
+        match key {
+            ConfigKey::Prefix => self.prefix = default.prefix,
+            ConfigKey::IdleTimeout => self.idle_timeout = default.idle_timeout,
+            ConfigKey::Volume => self.volume = default.volume,
+            ConfigKey::AnnounceChannel => self.announce_channel = default.announce_channel,
+            ConfigKey::MaxQueueLength => self.max_queue_length = default.max_queue_length,
+            ConfigKey::MaxUserSongs => self.max_user_songs = default.max_user_songs,
+            ConfigKey::MaxSongDuration => self.max_song_duration = default.max_song_duration,
+            ConfigKey::NowPlaying => self.now_playing = default.now_playing,
+            ConfigKey::VoteSkip => self.vote_skip = default.vote_skip,
+            ConfigKey::VoteSkipRatio => self.vote_skip_ratio = default.vote_skip_ratio,
+            ConfigKey::DjRole => self.dj_role = default.dj_role,
+            ConfigKey::FairQueue => self.fair_queue = default.fair_queue,
+            ConfigKey::Autoplay => self.autoplay = default.autoplay,
+            ConfigKey::Normalize => self.normalize = default.normalize,
+            ConfigKey::Crossfade => self.crossfade = default.crossfade,
+        }
+    }
+}
+
+/// Configs which were loaded from storage already
+static CONFIGS: Lazy<Mutex<HashMap<GuildId, GuildConfig>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn config_path(guild_id: GuildId) -> PathBuf {
+    Path::new(CONFIG_DIR).join(format!("{}.json", guild_id))
+}
+
+/// Gets the config of `guild_id`, falling back to the defaults if it can't be loaded
+pub async fn get_config(guild_id: GuildId) -> GuildConfig {
+    cached_config(&mut *CONFIGS.lock().await, guild_id)
+        .await
+        .clone()
+}
+
+/// The cached config of `guild_id`, which is loaded on first use
+async fn cached_config(
+    configs: &mut HashMap<GuildId, GuildConfig>,
+    guild_id: GuildId,
+) -> &mut GuildConfig {
+    match configs.entry(guild_id) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let loaded = storage::load(&config_path(guild_id)).await;
+            emit!(&loaded, Level::WARN);
+
+            entry.insert(loaded.ok().flatten().unwrap_or_default())
+        }
+    }
+}
+
+/// Changes the config of `guild_id` with `f` and stores the result.
+///
+/// The cache stays locked until the file is written, so concurrent changes
+/// don't undo each other.
+#[instrument(skip(f))]
+pub async fn update_config<F>(guild_id: GuildId, f: F) -> SunnyResult<GuildConfig>
+where
+    F: FnOnce(&mut GuildConfig) -> SunnyResult<()>,
+{
+    let mut configs = CONFIGS.lock().await;
+    let cached = cached_config(&mut configs, guild_id).await;
+
+    let mut config = cached.clone();
+    f(&mut config)?;
+
+    storage::save(&config_path(guild_id), &config).await?;
+    *cached = config.clone();
+
+    Ok(config)
+}
+
+#[instrument]
+pub async fn set_config(guild_id: GuildId, key: ConfigKey, value: &str) -> SunnyResult<String> {
+    let config = update_config(guild_id, |c| c.set(key, value)).await?;
+
+    Ok(config.get(key))
+}
+
+/// Resets `key` to its default, or every setting if there is none
+#[instrument]
+pub async fn reset_config(guild_id: GuildId, key: Option<ConfigKey>) -> SunnyResult<()> {
+    update_config(guild_id, |c| {
+        match key {
+            Some(key) => c.reset(key),
+            None => *c = GuildConfig::default(),
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The command prefix of the guild, if any, or the global [`CMD_PREFIX`]
+pub async fn get_prefix(guild_id: Option<GuildId>) -> String {
+    match guild_id {
+        Some(guild_id) => get_config(guild_id).await.prefix,
+        None => None,
+    }
+    .unwrap_or_else(|| CMD_PREFIX.clone())
+}
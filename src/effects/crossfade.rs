@@ -0,0 +1,182 @@
+//! # Crossfade
+//! Fades from one song into the next instead of leaving a gap between them.
+//! The next song starts early while the volumes of both songs are ramped.
+//! Guilds opt in with `config set crossfade 5s`.
+
+use std::{collections::HashMap, time::Duration};
+
+use once_cell::sync::Lazy;
+use serenity::{client::Context, model::id::GuildId, prelude::Mutex};
+use songbird::tracks::{PlayMode, TrackHandle, TrackState};
+use tracing::instrument;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::{
+    config::get_config,
+    get_timeline,
+    loudness::get_gain,
+    queue::{get_loop_mode, LoopMode},
+};
+
+/// Longest crossfade a guild can set
+pub const MAX_CROSSFADE: Duration = Duration::from_secs(10);
+/// How often the volumes are adjusted while fading
+pub const CROSSFADE_STEP: Duration = Duration::from_millis(250);
+
+/// The song which started early to fade in, per guild
+static FADING_IN: Lazy<Mutex<HashMap<GuildId, TrackHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How much longer `track` plays, which speed presets make differ from the rest of its song
+async fn remaining(state: &TrackState, track: &TrackHandle) -> Option<Duration> {
+    let duration = track.metadata().duration?;
+    let end = get_timeline(track).await.position(duration);
+
+    Some(end.saturating_sub(state.position))
+}
+
+/// Whether `track` is playing and close enough to its end to fade out at any crossfade
+pub async fn is_ending(state: &TrackState, track: &TrackHandle) -> bool {
+    state.playing == PlayMode::Play
+        && matches!(remaining(state, track).await, Some(r) if r <= MAX_CROSSFADE)
+}
+
+async fn get_queue(ctx: &Context, guild_id: GuildId) -> SunnyResult<Vec<TrackHandle>> {
+    Ok(songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue())
+}
+
+/// Sets the volume of `track` to `share` of what it would play at
+async fn fade(track: &TrackHandle, volume: u16, share: f32) -> SunnyResult<()> {
+    let volume = f32::from(volume) / 100.0 * get_gain(track).await * share;
+
+    track
+        .set_volume(volume)
+        .map_err(|e| SunnyError::log(format!("Failed to set volume: {}", e).as_str()))
+}
+
+/// Fades from `outgoing` into the next song once `outgoing` is within the
+/// guild's crossfade of its end, starting the next song if it isn't playing yet
+#[instrument(skip(ctx, state, outgoing))]
+pub async fn crossfade(
+    ctx: &Context,
+    guild_id: GuildId,
+    state: &TrackState,
+    outgoing: &TrackHandle,
+) -> SunnyResult<()> {
+    let remaining = match remaining(state, outgoing).await {
+        Some(remaining) => remaining,
+        None => return Ok(()),
+    };
+
+    let config = get_config(guild_id).await;
+
+    // A looped track doesn't end, so there's nothing to fade into
+    if config.crossfade.is_zero()
+        || remaining > config.crossfade
+        || get_loop_mode(guild_id).await == LoopMode::Track
+    {
+        return Ok(());
+    }
+
+    let next = match get_queue(ctx, guild_id).await?.as_slice() {
+        [current, next, ..] if current.uuid() == outgoing.uuid() => next.clone(),
+        _ => return Ok(()),
+    };
+
+    let progress = 1.0 - remaining.as_secs_f32() / config.crossfade.as_secs_f32();
+
+    fade(outgoing, config.volume, 1.0 - progress).await?;
+    fade(&next, config.volume, progress).await?;
+
+    let next_state = next
+        .get_info()
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to get track info: {}", e).as_str()))?;
+
+    if next_state.playing == PlayMode::Pause {
+        next.play()
+            .map_err(|e| SunnyError::log(format!("Failed to play track: {}", e).as_str()))?;
+
+        FADING_IN.lock().await.insert(guild_id, next);
+    }
+
+    Ok(())
+}
+
+/// Brings the song after `ended` to its full volume, wherever its fade in was at
+#[instrument(skip(ctx, ended))]
+pub async fn finish_crossfade(
+    ctx: &Context,
+    guild_id: GuildId,
+    ended: &TrackHandle,
+) -> SunnyResult<()> {
+    let config = get_config(guild_id).await;
+
+    FADING_IN.lock().await.remove(&guild_id);
+
+    if config.crossfade.is_zero() {
+        return Ok(());
+    }
+
+    // The queue might not have moved on from the ended song yet
+    let next = get_queue(ctx, guild_id)
+        .await?
+        .into_iter()
+        .find(|t| t.uuid() != ended.uuid());
+
+    match next {
+        Some(next) => fade(&next, config.volume, 1.0).await,
+        None => Ok(()),
+    }
+}
+
+/// Stops the song which is fading in once changes to the queue moved it away from
+/// being next. It starts over at its full volume when its turn comes.
+///
+/// Songs which were taken out of the queue are stopped by whoever removed them.
+#[instrument(skip(ctx))]
+pub async fn cancel_fade_in(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
+    let fading = match FADING_IN.lock().await.get(&guild_id) {
+        Some(track) => track.clone(),
+        None => return Ok(()),
+    };
+
+    let index = get_queue(ctx, guild_id)
+        .await?
+        .iter()
+        .position(|t| t.uuid() == fading.uuid());
+
+    // Still next, or playing already
+    if matches!(index, Some(0 | 1)) {
+        return Ok(());
+    }
+
+    FADING_IN.lock().await.remove(&guild_id);
+
+    if index.is_none() {
+        return Ok(());
+    }
+
+    fading
+        .pause()
+        .map_err(|e| SunnyError::log(format!("Failed to pause track: {}", e).as_str()))?;
+
+    fade(&fading, get_config(guild_id).await.volume, 1.0).await?;
+
+    if fading.is_seekable() {
+        fading
+            .seek_time(Duration::ZERO)
+            .map_err(|e| SunnyError::log(format!("Failed to rewind track: {}", e).as_str()))?;
+    }
+
+    Ok(())
+}
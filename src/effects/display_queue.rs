@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use serenity::{
-    builder::CreateActionRow,
+    builder::{CreateActionRow, CreateEmbed},
     client::Context,
     futures::prelude::*,
     model::{
@@ -9,29 +9,51 @@ use serenity::{
         id::{ChannelId, GuildId},
         interactions::{message_component::ButtonStyle, InteractionResponseType},
     },
+    prelude::Mentionable,
 };
 use songbird::tracks::TrackHandle;
 use tracing::instrument;
 
-use crate::utils::{SunnyError, SunnyResult};
+use crate::{
+    effects::config::get_config,
+    structs::Requester,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    queue::{get_loop_mode, LoopMode},
+    *,
+};
 
-use super::*;
+pub(super) const PREV_ID: &str = "q_prev";
+pub(super) const NEXT_ID: &str = "q_next";
 
-const PREV_ID: &str = "q_prev";
-const NEXT_ID: &str = "q_next";
+/// A queued track and who queued it
+type Entry = (TrackHandle, Option<Requester>);
 
-fn generate_embed(queue: &[TrackHandle], page: usize) -> serenity::builder::CreateEmbed {
+fn requested_by(requester: Option<Requester>) -> String {
+    requester
+        .map(|r| format!(" ({})", r.user_id.mention()))
+        .unwrap_or_default()
+}
+
+fn generate_embed(
+    queue: &[Entry],
+    page: usize,
+    loop_mode: LoopMode,
+    fair_queue: bool,
+) -> CreateEmbed {
     let mut titles = Vec::with_capacity(10);
     let mut artists = Vec::with_capacity(10);
     let mut durs = Vec::with_capacity(10);
     let total_duration = queue.iter().fold(Duration::default(), |a, b| {
-        a + b.metadata().duration.unwrap_or_default()
+        a + b.0.metadata().duration.unwrap_or_default()
     });
 
-    for (i, track) in queue.iter().enumerate().skip(1 + page * 10).take(10) {
+    for (i, (track, requester)) in queue.iter().enumerate().skip(1 + page * 10).take(10) {
         let m = track.metadata();
 
-        let title = format!("**{}.** {}\n", i, get_title(m));
+        let title = format!("**{}.** {}{}\n", i, get_title(m), requested_by(*requester));
         titles.push(title);
 
         let artist = format!("{}\n", get_artist(m));
@@ -44,16 +66,17 @@ fn generate_embed(queue: &[TrackHandle], page: usize) -> serenity::builder::Crea
         durs.push(format!("[{}:{:02}]\n", minutes, seconds));
     }
 
-    let mut e = serenity::builder::CreateEmbed::default();
+    let mut e = CreateEmbed::default();
     e.author(|a| a.name("Queueueueueu"));
 
-    if let Some(track) = queue.first() {
+    if let Some((track, requester)) = queue.first() {
         let m = track.metadata();
 
         e.description(format!(
-            "**Now Playing:** {} by {}",
+            "**Now Playing:** {} by {}{}",
             get_title(m),
-            get_artist(m)
+            get_artist(m),
+            requested_by(*requester)
         ));
     }
 
@@ -80,19 +103,29 @@ fn generate_embed(queue: &[TrackHandle], page: usize) -> serenity::builder::Crea
         let seconds = total_duration.as_secs() % 60;
         let minutes = total_duration.as_secs() / 60;
 
-        f.text(format!(
+        let mut text = format!(
             "Page {}/{} | Total Duration: {:02}:{:02}",
             page + 1,
             (queue.len() / 10 + 1),
             minutes,
             seconds,
-        ))
+        );
+
+        if loop_mode != LoopMode::Off {
+            text.push_str(&format!(" | Loop: {}", loop_mode));
+        }
+
+        if fair_queue {
+            text.push_str(" | Fair queue: requesters take turns");
+        }
+
+        f.text(text)
     });
 
     e
 }
 
-fn build_action_row(page: usize, queue_len: usize) -> CreateActionRow {
+pub(super) fn build_action_row(page: usize, queue_len: usize) -> CreateActionRow {
     let pages = queue_len / 10;
     let mut row = CreateActionRow::default();
 
@@ -134,8 +167,8 @@ fn build_action_row(page: usize, queue_len: usize) -> CreateActionRow {
 }
 
 #[instrument(skip(ctx))]
-async fn get_queue(ctx: &Context, guild_id: GuildId) -> SunnyResult<Vec<TrackHandle>> {
-    Ok(songbird::get(ctx)
+async fn get_queue(ctx: &Context, guild_id: GuildId) -> SunnyResult<Vec<Entry>> {
+    let current_queue = songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
         .get(guild_id)
@@ -143,7 +176,16 @@ async fn get_queue(ctx: &Context, guild_id: GuildId) -> SunnyResult<Vec<TrackHan
         .lock()
         .await
         .queue()
-        .current_queue())
+        .current_queue();
+
+    let mut queue = Vec::with_capacity(current_queue.len());
+
+    for track in current_queue {
+        let requester = get_requester(&track).await;
+        queue.push((track, requester));
+    }
+
+    Ok(queue)
 }
 
 /// Sends an interactive queue embed and interactions
@@ -155,20 +197,35 @@ pub async fn send_embed(
 ) -> SunnyResult<()> {
     // Retrieve the current queue
     let cq = get_queue(ctx, guild_id).await?;
+    let loop_mode = get_loop_mode(guild_id).await;
+    let fair_queue = get_config(guild_id).await.fair_queue;
 
     // Send initial queue message
     let message = channel_id
         .send_message(&ctx.http, |m| {
             m.components(|c| c.set_action_rows(vec![build_action_row(0, cq.len())]));
-            m.set_embed(generate_embed(&cq, 0))
+            m.set_embed(generate_embed(&cq, 0, loop_mode, fair_queue))
         })
         .await
         .map_err(|e| SunnyError::log(format!("Unable to send queue message: {:?}", e).as_str()))?;
 
-    await_interactions(ctx, message, guild_id).await
+    paginate(ctx, message, move |page| async move {
+        let cq = get_queue(ctx, guild_id).await?;
+        let loop_mode = get_loop_mode(guild_id).await;
+        let fair_queue = get_config(guild_id).await.fair_queue;
+
+        Ok((generate_embed(&cq, page, loop_mode, fair_queue), cq.len()))
+    })
+    .await
 }
 
-async fn await_interactions(ctx: &Context, mut msg: Message, guild_id: GuildId) -> SunnyResult<()> {
+/// Lets members page through `msg` with the buttons of [`build_action_row`] for an hour.
+/// `render` builds the embed of a page, along with the amount of entries the pages cover.
+pub(super) async fn paginate<F, Fut>(ctx: &Context, mut msg: Message, render: F) -> SunnyResult<()>
+where
+    F: Fn(usize) -> Fut,
+    Fut: Future<Output = SunnyResult<(CreateEmbed, usize)>>,
+{
     // Currently shown page
     let mut page: usize = 0;
 
@@ -192,14 +249,14 @@ async fn await_interactions(ctx: &Context, mut msg: Message, guild_id: GuildId)
             continue;
         }
 
-        let cq = get_queue(ctx, guild_id).await?;
+        let (embed, len) = render(page).await?;
 
         // Change the embed + buttons after page change
         mci.create_interaction_response(&ctx.http, |cir| {
             cir.kind(InteractionResponseType::UpdateMessage)
                 .interaction_response_data(|m| {
-                    m.add_embed(generate_embed(&cq, page));
-                    m.components(|c| c.set_action_rows(vec![build_action_row(page, cq.len())]))
+                    m.add_embed(embed);
+                    m.components(|c| c.set_action_rows(vec![build_action_row(page, len)]))
                 })
         })
         .await
@@ -208,16 +265,12 @@ async fn await_interactions(ctx: &Context, mut msg: Message, guild_id: GuildId)
         })?;
     }
 
-    let guild_id = msg
-        .guild_id
-        .ok_or_else(|| SunnyError::log("message guild id could not be found"))?;
-
-    let cq = get_queue(ctx, guild_id).await?;
+    let (embed, _) = render(page).await?;
 
     // Remove buttons after timeout
     msg.edit(&ctx.http, |e| {
         e.components(|c| c);
-        e.set_embed(generate_embed(&cq, page))
+        e.set_embed(embed)
     })
     .await
     .map_err(|e| SunnyError::log(format!("Unable clear buttons {:?}", e).as_str()))?;
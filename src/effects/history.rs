@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    futures::prelude::*,
+    model::id::{ChannelId, GuildId},
+    prelude::{Mentionable, Mutex},
+};
+use songbird::{input::Metadata, tracks::TrackHandle};
+use tracing::instrument;
+
+use crate::{
+    structs::SavedTrack,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    display_queue::{build_action_row, paginate},
+    format_duration, get_artist, get_title,
+    queue::{self, EnqueueAt},
+};
+
+/// Amount of played tracks remembered per guild
+const HISTORY_LENGTH: usize = 50;
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub track: SavedTrack,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Played tracks per guild, the most recent one first
+static HISTORY: Lazy<Mutex<HashMap<GuildId, VecDeque<HistoryEntry>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Remembers that `track` finished playing
+pub async fn record(guild_id: GuildId, track: &TrackHandle) {
+    let track = match SavedTrack::from_track(track).await {
+        Some(track) => track,
+        None => return,
+    };
+
+    let mut history = HISTORY.lock().await;
+    let entries = history.entry(guild_id).or_default();
+
+    entries.push_front(HistoryEntry {
+        track,
+        finished_at: Utc::now(),
+    });
+    entries.truncate(HISTORY_LENGTH);
+}
+
+pub async fn get_history(guild_id: GuildId) -> Vec<HistoryEntry> {
+    HISTORY
+        .lock()
+        .await
+        .get(&guild_id)
+        .map(|entries| entries.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Adds the last played track to the front of the queue, returning its metadata
+#[instrument(skip(ctx))]
+pub async fn play_previous(ctx: &Context, guild_id: GuildId) -> SunnyResult<Metadata> {
+    let entry = HISTORY
+        .lock()
+        .await
+        .get(&guild_id)
+        .and_then(VecDeque::front)
+        .cloned()
+        .ok_or_else(|| SunnyError::user("Nothing played yet"))?;
+
+    let requester = entry.track.requester;
+    let metadata = Metadata::from(entry.track);
+
+    queue::play_tracks(
+        ctx,
+        guild_id,
+        vec![metadata.clone()],
+        requester,
+        EnqueueAt::Front,
+    )
+    .await?;
+
+    // Only forget the track once it's queued again, so a failed enqueue can be retried.
+    // Tracks which finished in the meantime went in front of it.
+    if let Some(entries) = HISTORY.lock().await.get_mut(&guild_id) {
+        if let Some(i) = entries
+            .iter()
+            .position(|e| e.finished_at == entry.finished_at)
+        {
+            entries.remove(i);
+        }
+    }
+
+    Ok(metadata)
+}
+
+fn generate_embed(history: &[HistoryEntry], page: usize) -> CreateEmbed {
+    let lines = history
+        .iter()
+        .enumerate()
+        .skip(page * 10)
+        .take(10)
+        .map(|(i, entry)| {
+            let m = Metadata::from(entry.track.clone());
+            let requester = entry
+                .track
+                .requester
+                .map(|r| format!(", requested by {}", r.mention()))
+                .unwrap_or_default();
+
+            format!(
+                "**{}.** {} by {} [{}] <t:{}:R>{}",
+                i + 1,
+                get_title(&m),
+                get_artist(&m),
+                format_duration(m.duration.unwrap_or_default()),
+                entry.finished_at.timestamp(),
+                requester
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut e = CreateEmbed::default();
+    e.author(|a| a.name("Recently played"));
+
+    if lines.is_empty() {
+        e.description("Nothing played yet");
+    } else {
+        e.description(lines.join("\n"));
+    }
+
+    e.footer(|f| {
+        f.text(format!(
+            "Page {}/{}",
+            page + 1,
+            history.len().saturating_sub(1) / 10 + 1
+        ))
+    });
+
+    e
+}
+
+/// Sends an embed of the recently played tracks, with the same pages as the queue embed
+#[instrument(skip(ctx), name = "history_embed")]
+pub async fn send_embed(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> SunnyResult<()> {
+    let history = get_history(guild_id).await;
+
+    // The queue embed doesn't count the current track, so leave one out
+    let pages_len = history.len().saturating_sub(1);
+
+    let message = channel_id
+        .send_message(&ctx.http, |m| {
+            m.components(|c| c.set_action_rows(vec![build_action_row(0, pages_len)]));
+            m.set_embed(generate_embed(&history, 0))
+        })
+        .await
+        .map_err(|e| {
+            SunnyError::log(format!("Unable to send history message: {:?}", e).as_str())
+        })?;
+
+    paginate(ctx, message, |page| {
+        future::ready(Ok((generate_embed(&history, page), pages_len)))
+    })
+    .await
+}
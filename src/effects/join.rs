@@ -8,8 +8,12 @@ use serenity::prelude::Mutex;
 use songbird::{Call, Event, TrackEvent};
 use tracing::instrument;
 
+use super::{crossfade::CROSSFADE_STEP, persist};
 use crate::{
-    handlers::{TimeoutHandler, TrackPlayNotifier},
+    handlers::{
+        AutoplayHandler, CrossfadeHandler, HistoryRecorder, LoopQueueHandler, LoopTrackHandler,
+        NormalizeHandler, PrefetchHandler, QueueSnapshotter, TimeoutHandler, TrackPlayNotifier,
+    },
     structs::EventConfig,
     utils::{SunnyError, SunnyResult},
 };
@@ -26,6 +30,56 @@ async fn add_events(cfg: &EventConfig, call_m: Arc<Mutex<Call>>) {
         TrackPlayNotifier { cfg: cfg.clone() },
     );
 
+    call.add_global_event(
+        Event::Track(TrackEvent::Play),
+        LoopTrackHandler { cfg: cfg.clone() },
+    );
+
+    call.add_global_event(
+        Event::Track(TrackEvent::Play),
+        NormalizeHandler { cfg: cfg.clone() },
+    );
+
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        LoopQueueHandler { cfg: cfg.clone() },
+    );
+
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        HistoryRecorder { cfg: cfg.clone() },
+    );
+
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        AutoplayHandler { cfg: cfg.clone() },
+    );
+
+    // Periodic events see every track, the crossfade picks the ones about to end
+    for event in [
+        Event::Track(TrackEvent::End),
+        Event::Periodic(CROSSFADE_STEP, None),
+    ] {
+        call.add_global_event(event, CrossfadeHandler { cfg: cfg.clone() });
+    }
+
+    // The periodic event catches stream urls expiring while a long song plays
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Periodic(Duration::from_secs(5 * 60), None),
+    ] {
+        call.add_global_event(event, PrefetchHandler { cfg: cfg.clone() });
+    }
+
+    // Play and End cover changing tracks, the periodic event keeps the position fresh
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Track(TrackEvent::End),
+        Event::Periodic(Duration::from_secs(15), None),
+    ] {
+        call.add_global_event(event, QueueSnapshotter { cfg: cfg.clone() });
+    }
+
     call.add_global_event(
         Event::Periodic(Duration::from_secs(60), None),
         TimeoutHandler {
@@ -51,6 +105,7 @@ pub async fn join(cfg: &EventConfig) -> SunnyResult<Arc<Mutex<Call>>> {
         .map_err(|e| SunnyError::user_and_log("Failed to join channel", e.to_string().as_str()))?;
 
     add_events(cfg, call_m.clone()).await;
+    persist::start_session(cfg).await;
 
     Ok(call_m)
 }
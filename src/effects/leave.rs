@@ -3,12 +3,21 @@ use tracing::instrument;
 
 use crate::utils::{SunnyError, SunnyResult};
 
+use super::{
+    persist,
+    queue::{reset_filter, reset_loop_mode},
+};
+
 #[instrument(skip(ctx))]
 pub async fn leave(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
     let songbird = songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get Songbird"))?;
 
+    reset_loop_mode(guild_id).await;
+    reset_filter(guild_id).await;
+    persist::end_session(guild_id).await?;
+
     songbird
         .remove(guild_id)
         .await
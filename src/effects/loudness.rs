@@ -0,0 +1,121 @@
+//! # Loudness
+//! Evens out how loud songs are, so the next song isn't suddenly much quieter
+//! or louder. Every song's loudness is measured and its volume set accordingly.
+//! Guilds opt in with `config set normalize on`.
+
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use serenity::{client::Context, model::id::GuildId, prelude::Mutex};
+use songbird::tracks::TrackHandle;
+use tracing::{event, instrument, Level};
+
+use crate::{
+    emit, sources,
+    structs::TrackGain,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::config::get_config;
+
+/// Loudness every song is brought to, in LUFS
+const TARGET_LOUDNESS: f64 = -16.0;
+/// Most a quiet song is turned up, in dB. Turning up further mostly makes it clip.
+const MAX_BOOST: f64 = 6.0;
+/// Most a loud song is turned down, in dB
+const MAX_CUT: f64 = 20.0;
+
+/// Gains of every song measured so far, by source url
+static GAINS: Lazy<Mutex<HashMap<String, f32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// Source urls which are being measured right now
+static MEASURING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// The factor the volume of `track` is multiplied with, 1 if it isn't normalized
+pub async fn get_gain(track: &TrackHandle) -> f32 {
+    track
+        .typemap()
+        .read()
+        .await
+        .get::<TrackGain>()
+        .copied()
+        .unwrap_or(1.0)
+}
+
+/// Turns a loudness in LUFS into the volume factor which brings it to [`TARGET_LOUDNESS`]
+fn gain(loudness: f64) -> f32 {
+    let db = (TARGET_LOUDNESS - loudness).clamp(-MAX_CUT, MAX_BOOST);
+
+    10_f64.powf(db / 20.0) as f32
+}
+
+/// Looks up the gain of `url`, measuring it if it wasn't before.
+/// Nothing is returned while another task is measuring it already.
+async fn find_gain(url: &str) -> SunnyResult<Option<f32>> {
+    if let Some(gain) = GAINS.lock().await.get(url) {
+        return Ok(Some(*gain));
+    }
+
+    if !MEASURING.lock().await.insert(url.to_string()) {
+        return Ok(None);
+    }
+
+    let loudness = sources::loudness(url).await;
+    MEASURING.lock().await.remove(url);
+
+    let gain = gain(loudness?);
+    GAINS.lock().await.insert(url.to_string(), gain);
+
+    Ok(Some(gain))
+}
+
+/// Sets the volume of `track` so it's as loud as any other normalized song
+#[instrument(skip(track))]
+async fn normalize(guild_id: GuildId, track: &TrackHandle) -> SunnyResult<()> {
+    if track.typemap().read().await.contains_key::<TrackGain>() {
+        return Ok(());
+    }
+
+    let url = match &track.metadata().source_url {
+        Some(url) if !url.starts_with(sources::RADIO_PREFIX) => url.clone(),
+        _ => return Ok(()),
+    };
+
+    let gain = match find_gain(&url).await? {
+        Some(gain) => gain,
+        None => return Ok(()),
+    };
+
+    track.typemap().write().await.insert::<TrackGain>(gain);
+
+    let volume = f32::from(get_config(guild_id).await.volume) / 100.0;
+
+    // The track might have been skipped while it was measured
+    track.set_volume(volume * gain).ok();
+
+    Ok(())
+}
+
+/// Normalizes the current and the next song, so the next one is ready once it starts
+#[instrument(skip(ctx))]
+pub async fn normalize_upcoming(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
+    if !get_config(guild_id).await.normalize {
+        return Ok(());
+    }
+
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    // A song which can't be measured just keeps its volume
+    for track in current_queue.iter().take(2) {
+        emit!(normalize(guild_id, track).await, Level::WARN);
+    }
+
+    Ok(())
+}
@@ -2,39 +2,91 @@
 //! Effects contains the main functionality of Sunny
 //!
 
+pub mod autoplay;
+pub mod config;
+pub mod crossfade;
 mod deafen;
 pub mod display_queue;
+pub mod history;
 mod join;
 mod leave;
+pub mod loudness;
 pub mod now_playing;
+pub mod persist;
+pub mod playlists;
+pub mod prefetch;
 pub mod queue;
+pub mod search;
+pub mod transfer;
 
 pub use deafen::deafen;
 pub use join::join;
 pub use leave::leave;
 
-use songbird::input::Metadata;
+use songbird::{input::Metadata, tracks::TrackHandle};
 use std::time::Duration;
 
+use crate::{
+    sources::Timeline,
+    structs::{Autoplayed, Requester, TrackTimeline},
+};
+
 /// `split_duration` splits a [`Duration`] into a (minutes, seconds) tuple
 const fn split_duration(d: Duration) -> (u64, u64) {
     (d.as_secs() / 60, d.as_secs() % 60)
 }
 
+/// Formats a [`Duration`] as `minutes:seconds`
+pub fn format_duration(d: Duration) -> String {
+    let (minutes, seconds) = split_duration(d);
+    format!("{}:{:02}", minutes, seconds)
+}
+
+/// Formats a [`Duration`] in a rough, readable way, like `2h14m`
+pub fn format_total_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match (secs / 3600, secs % 3600 / 60) {
+        (0, 0) => format!("{}s", secs),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h{:02}m", hours, minutes),
+    }
+}
+
 fn get_title(m: &Metadata) -> &str {
     m.track
         .as_deref()
-        .or_else(|| m.title.as_deref())
+        .or(m.title.as_deref())
         .unwrap_or("Unknown Title")
 }
 
 fn get_artist(m: &Metadata) -> &str {
     m.artist
         .as_deref()
-        .or_else(|| m.channel.as_deref())
+        .or(m.channel.as_deref())
         .unwrap_or("Unknown Artist")
 }
 
+/// Who queued `track` and when, if anyone did
+pub async fn get_requester(track: &TrackHandle) -> Option<Requester> {
+    track.typemap().read().await.get::<Requester>().copied()
+}
+
+/// Maps the position of `track` to where it is in its song
+pub async fn get_timeline(track: &TrackHandle) -> Timeline {
+    track
+        .typemap()
+        .read()
+        .await
+        .get::<TrackTimeline>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Whether Sunny picked `track` herself, see [`autoplay`]
+pub async fn is_autoplayed(track: &TrackHandle) -> bool {
+    track.typemap().read().await.contains_key::<Autoplayed>()
+}
+
 pub fn get_song(m: &Metadata) -> String {
     format!("{} by {}", get_title(m), get_artist(m))
 }
@@ -3,19 +3,35 @@ use std::time::Duration;
 use serenity::{
     client::Context,
     model::id::{ChannelId, GuildId},
+    prelude::Mentionable,
+};
+use songbird::{
+    input::Metadata,
+    tracks::{TrackHandle, TrackState},
 };
-use songbird::{input::Metadata, tracks::TrackHandle};
 use tracing::instrument;
 
-use crate::utils::{SunnyError, SunnyResult};
+use crate::{
+    sources::{self, StreamInfo},
+    structs::Requester,
+    utils::{SunnyError, SunnyResult},
+};
 
-use super::{get_artist, get_title, split_duration};
+use super::{
+    get_artist, get_requester, get_timeline, get_title, is_autoplayed,
+    queue::{get_loop_mode, LoopMode},
+    split_duration,
+};
 
 /// Generates an embed to show what's currently playing and what is up next
 pub fn generate_embed(
     m: &Metadata,
-    pos: Duration,
+    info: &TrackState,
+    live: Option<&StreamInfo>,
     m2: Option<&Metadata>,
+    loop_mode: LoopMode,
+    requester: Option<Requester>,
+    autoplayed: bool,
 ) -> serenity::builder::CreateEmbed {
     let mut e = serenity::builder::CreateEmbed::default();
 
@@ -25,7 +41,17 @@ pub fn generate_embed(
 
     let artist = get_artist(m);
 
-    e.title(format!("{} by {}", title, artist));
+    // Live streams tell what's on themselves
+    match live {
+        Some(live) => {
+            let station = live.station.as_deref().unwrap_or(title);
+            e.title(live.title.as_deref().unwrap_or(station));
+            e.author(|a| a.name(format!("Live on {}:", station)));
+        }
+        None => {
+            e.title(format!("{} by {}", title, artist));
+        }
+    }
 
     if let Some(thumbnail) = &m.thumbnail {
         e.thumbnail(thumbnail);
@@ -35,23 +61,66 @@ pub fn generate_embed(
         e.url(url);
     }
 
-    let (curr_min, curr_sec) = split_duration(pos);
-    let (max_min, max_sec) = split_duration(m.duration.unwrap_or_default());
-    let progress = format!(
-        "**Current Time:** {}:{:02} / {}:{:02}",
-        curr_min, curr_sec, max_min, max_sec
-    );
+    let progress = if live.is_some() {
+        "**LIVE**".to_string()
+    } else {
+        let (curr_min, curr_sec) = split_duration(info.position);
+        let (max_min, max_sec) = split_duration(m.duration.unwrap_or_default());
+
+        format!(
+            "**Current Time:** {}:{:02} / {}:{:02}",
+            curr_min, curr_sec, max_min, max_sec
+        )
+    };
 
     let up_next = m2
         .map(|m2| format!("**Up Next:** {} by {}", get_title(m2), get_artist(m2)))
         .unwrap_or_default();
 
-    e.description(&[progress, up_next].join("\n"));
+    let looping = match loop_mode {
+        LoopMode::Off => String::new(),
+        mode => format!("**Loop:** {}", mode),
+    };
+
+    let volume = format!("**Volume:** {:.0}%", info.volume * 100.0);
+
+    let requested = if autoplayed {
+        "**Autoplay:** picked because the queue ran out".to_string()
+    } else {
+        requester
+            .map(|r| {
+                format!(
+                    "**Requested by:** {} <t:{}:R>",
+                    r.user_id.mention(),
+                    r.enqueued_at.timestamp()
+                )
+            })
+            .unwrap_or_default()
+    };
+
+    let lines = [progress, volume, requested, looping, up_next];
+    e.description(
+        lines
+            .iter()
+            .filter(|l| !l.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
     e.timestamp(&chrono::Utc::now());
 
     e
 }
 
+/// What `track` announces about itself, if it's a live stream
+fn live_info(guild_id: GuildId, track: &TrackHandle) -> Option<StreamInfo> {
+    track
+        .metadata()
+        .source_url
+        .as_deref()
+        .and_then(|url| sources::stream_info(guild_id, url))
+}
+
 /// Gets the current and next up song's [`TrackHandle`].
 #[instrument(skip(ctx))]
 async fn get_songs(
@@ -85,21 +154,31 @@ pub async fn send_embed(
 
     let current = current.ok_or_else(|| SunnyError::user("No song playing"))?;
 
-    let position = current
+    let mut info = current
         .get_info()
         .await
-        .map_err(|e| SunnyError::log(format!("a: {:?}", e).as_str()))? // * hackers on diefstal e(stradiol)n heling
-        .position;
+        .map_err(|e| SunnyError::log(format!("a: {:?}", e).as_str()))?; // * hackers on diefstal e(stradiol)n heling
+
+    // Show where the song is, which speed presets play faster or slower
+    let timeline = get_timeline(&current).await;
+    info.position = timeline.song_position(info.position);
 
     let next_metadata = next.map(|t| t.metadata().clone());
+    let loop_mode = get_loop_mode(guild_id).await;
+    let requester = get_requester(&current).await;
+    let autoplayed = is_autoplayed(&current).await;
 
     // e
     let mut m = channel_id
         .send_message(&ctx.http, |m| {
             m.set_embed(generate_embed(
                 current.metadata(),
-                position,
+                &info,
+                live_info(guild_id, &current).as_ref(),
                 next_metadata.as_ref(),
+                loop_mode,
+                requester,
+                autoplayed,
             ))
         })
         .await
@@ -112,12 +191,22 @@ pub async fn send_embed(
         tokio::time::sleep(Duration::from_secs(10)).await;
 
         // Will error when finished
-        if let Ok(info) = current.get_info().await {
-            let embed = generate_embed(current.metadata(), info.position, next_metadata.as_ref());
+        if let Ok(mut info) = current.get_info().await {
+            info.position = timeline.song_position(info.position);
+
+            let embed = generate_embed(
+                current.metadata(),
+                &info,
+                live_info(guild_id, &current).as_ref(),
+                next_metadata.as_ref(),
+                get_loop_mode(guild_id).await,
+                requester,
+                autoplayed,
+            );
 
             m.edit(&c.http, |e| e.set_embed(embed)).await.ok();
         } else {
-            #[cfg_attr(not(feature = "url/cache"), allow(clippy::unwrap_used))]
+            #[allow(clippy::unwrap_used)]
             m.delete(&c.http).await.unwrap();
             break;
         }
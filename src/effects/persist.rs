@@ -0,0 +1,225 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::Mutex,
+};
+use songbird::input::Metadata;
+use tracing::{event, instrument, Level};
+
+use crate::{
+    emit, storage,
+    structs::{EventConfig, SavedTrack},
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    deafen, get_timeline, join,
+    queue::{self, EnqueueAt, SeekTarget},
+};
+
+const QUEUES_DIR: &str = "queues";
+
+/// Everything needed to pick a guild's session back up after a restart
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedQueue {
+    guild_id: GuildId,
+    text_channel_id: ChannelId,
+    voice_channel_id: ChannelId,
+    /// Position in the first track
+    position: Duration,
+    tracks: Vec<SavedTrack>,
+}
+
+/// Text and voice channel of every guild Sunny is currently in a call in
+static SESSIONS: Lazy<Mutex<HashMap<GuildId, (ChannelId, ChannelId)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Guilds whose queue is being restored, saving it halfway would lose the rest
+static RESTORING: Lazy<Mutex<HashSet<GuildId>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn queue_path(guild_id: GuildId) -> PathBuf {
+    Path::new(QUEUES_DIR).join(format!("{}.json", guild_id))
+}
+
+/// Starts persisting the queue of the call described by `cfg`
+pub async fn start_session(cfg: &EventConfig) {
+    SESSIONS
+        .lock()
+        .await
+        .insert(cfg.guild_id, (cfg.text_channel_id, cfg.voice_channel_id));
+}
+
+/// Stops persisting the queue of `guild_id` and forgets the saved one
+#[instrument]
+pub async fn end_session(guild_id: GuildId) -> SunnyResult<()> {
+    SESSIONS.lock().await.remove(&guild_id);
+
+    storage::remove(&queue_path(guild_id)).await
+}
+
+#[instrument(skip(ctx))]
+async fn snapshot(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
+    let (text_channel_id, voice_channel_id) = match SESSIONS.lock().await.get(&guild_id) {
+        Some(channels) => *channels,
+        None => return Ok(()),
+    };
+
+    if RESTORING.lock().await.contains(&guild_id) {
+        return Ok(());
+    }
+
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    // Nothing worth coming back for
+    if current_queue.is_empty() {
+        return storage::remove(&queue_path(guild_id)).await;
+    }
+
+    // Restoring seeks to where the song was, whatever speed it played at
+    let position = match current_queue.first() {
+        Some(track) => match track.get_info().await {
+            Ok(info) => get_timeline(track).await.song_position(info.position),
+            Err(_) => Duration::ZERO,
+        },
+        None => Duration::ZERO,
+    };
+
+    let mut tracks = Vec::with_capacity(current_queue.len());
+
+    for track in &current_queue {
+        tracks.extend(SavedTrack::from_track(track).await);
+    }
+
+    let saved = SavedQueue {
+        guild_id,
+        text_channel_id,
+        voice_channel_id,
+        position,
+        tracks,
+    };
+
+    storage::save(&queue_path(guild_id), &saved).await
+}
+
+/// Saves the queue of `guild_id`, so it can be rebuilt by [`restore_queues`]
+pub async fn save_queue(ctx: &Context, guild_id: GuildId) {
+    emit!(snapshot(ctx, guild_id).await, Level::WARN);
+}
+
+#[instrument(skip(ctx))]
+async fn restore_queue(ctx: &Context, path: &Path) -> SunnyResult<()> {
+    let saved: SavedQueue = match storage::load(path).await? {
+        Some(saved) => saved,
+        None => return Ok(()),
+    };
+
+    let songbird = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?;
+
+    // Ready also fires on reconnects, when the call is still around
+    if songbird.get(saved.guild_id).is_some() {
+        return Ok(());
+    }
+
+    let call_m = join(&EventConfig {
+        ctx: ctx.clone(),
+        guild_id: saved.guild_id,
+        text_channel_id: saved.text_channel_id,
+        voice_channel_id: saved.voice_channel_id,
+    })
+    .await?;
+
+    deafen(call_m.clone()).await;
+
+    let playing = saved.tracks.first().map(|t| t.source_url.clone());
+
+    // Songs in a row by the same requester go in together
+    let mut runs: Vec<(Option<UserId>, Vec<Metadata>)> = Vec::new();
+
+    for track in saved.tracks {
+        match runs.last_mut() {
+            Some((requester, tracks)) if *requester == track.requester => {
+                tracks.push(Metadata::from(track));
+            }
+            _ => runs.push((track.requester, vec![Metadata::from(track)])),
+        }
+    }
+
+    RESTORING.lock().await.insert(saved.guild_id);
+
+    for (requester, tracks) in runs {
+        let res = queue::play_tracks(
+            ctx,
+            saved.guild_id,
+            tracks.clone(),
+            requester,
+            EnqueueAt::End,
+        )
+        .await;
+
+        if res.is_ok() {
+            continue;
+        }
+
+        // One song which can't be played anymore shouldn't take the others with it
+        for track in tracks {
+            let res =
+                queue::play_tracks(ctx, saved.guild_id, vec![track], requester, EnqueueAt::End)
+                    .await;
+
+            emit!(res, Level::WARN);
+        }
+    }
+
+    RESTORING.lock().await.remove(&saved.guild_id);
+    save_queue(ctx, saved.guild_id).await;
+
+    let current = call_m.lock().await.queue().current();
+
+    // The position belongs to the song which was playing, which might not have come back
+    if !saved.position.is_zero()
+        && current.map(|t| t.metadata().source_url.clone()) == Some(playing)
+    {
+        queue::seek(ctx, saved.guild_id, SeekTarget::To(saved.position)).await?;
+    }
+
+    saved
+        .text_channel_id
+        .say(&ctx.http, "Back again, picking up where I left off")
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to send message: {}", e).as_str()))?;
+
+    Ok(())
+}
+
+/// Rejoins every call Sunny was in before a restart and rebuilds its queue
+#[instrument(skip(ctx))]
+pub async fn restore_queues(ctx: &Context) -> SunnyResult<()> {
+    let paths = storage::list(Path::new(QUEUES_DIR)).await?;
+
+    event!(Level::INFO, count = paths.len(), "Restoring queues");
+
+    for path in paths {
+        // One broken queue shouldn't keep the others from coming back
+        emit!(restore_queue(ctx, &path).await, Level::WARN);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! # Saved Playlists
+//! Queues saved under a name to be played again later, either for a single
+//! member or for the whole server.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+};
+use songbird::input::Metadata;
+use tracing::instrument;
+
+use crate::{
+    storage,
+    structs::SavedTrack,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::queue::{self, EnqueueAt, PlaylistSummary};
+
+const PLAYLISTS_DIR: &str = "playlists";
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Who a saved playlist belongs to, names only have to be unique within one scope
+#[derive(Clone, Copy, Debug)]
+pub enum PlaylistScope {
+    User(UserId),
+    Guild(GuildId),
+}
+
+impl PlaylistScope {
+    fn dir(self) -> PathBuf {
+        match self {
+            Self::User(user_id) => Path::new(PLAYLISTS_DIR)
+                .join("users")
+                .join(user_id.to_string()),
+            Self::Guild(guild_id) => Path::new(PLAYLISTS_DIR)
+                .join("guilds")
+                .join(guild_id.to_string()),
+        }
+    }
+
+    fn path(self, name: &str) -> PathBuf {
+        self.dir().join(format!("{}.json", name))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedPlaylist {
+    pub name: String,
+    pub saved_by: UserId,
+    pub saved_at: DateTime<Utc>,
+    pub tracks: Vec<SavedTrack>,
+}
+
+impl SavedPlaylist {
+    pub fn duration(&self) -> Duration {
+        self.tracks.iter().filter_map(|t| t.duration).sum()
+    }
+}
+
+/// Names end up as file names, so only simple ones are allowed
+fn parse_name(name: &str) -> SunnyResult<String> {
+    let name = name.trim().to_lowercase();
+
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(name)
+    } else {
+        Err(SunnyError::user(
+            format!(
+                "Playlist names can be up to {} letters, numbers, `-` and `_`",
+                MAX_NAME_LENGTH
+            )
+            .as_str(),
+        ))
+    }
+}
+
+/// Gets the playlist `name` of `scope`
+#[instrument]
+pub async fn get_playlist(scope: PlaylistScope, name: &str) -> SunnyResult<SavedPlaylist> {
+    let name = parse_name(name)?;
+
+    storage::load(&scope.path(&name))
+        .await?
+        .ok_or_else(|| SunnyError::user(format!("There's no playlist called `{}`", name).as_str()))
+}
+
+/// Saves the queue of `guild_id` as `name`, replacing any playlist of that name.
+/// Returns the saved playlist.
+#[instrument(skip(ctx))]
+pub async fn save(
+    ctx: &Context,
+    guild_id: GuildId,
+    scope: PlaylistScope,
+    name: &str,
+    user_id: UserId,
+) -> SunnyResult<SavedPlaylist> {
+    let name = parse_name(name)?;
+
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::user("Not currently in a call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    let tracks = current_queue
+        .iter()
+        .filter_map(|t| SavedTrack::from_metadata(t.metadata()))
+        .collect::<Vec<_>>();
+
+    if tracks.is_empty() {
+        return Err(SunnyError::user("Nothing in the queue to save"));
+    }
+
+    let playlist = SavedPlaylist {
+        name,
+        saved_by: user_id,
+        saved_at: Utc::now(),
+        tracks,
+    };
+
+    storage::save(&scope.path(&playlist.name), &playlist).await?;
+
+    Ok(playlist)
+}
+
+/// Adds every song of the playlist `name` to the queue, requested by `requester`
+#[instrument(skip(ctx))]
+pub async fn load(
+    ctx: &Context,
+    guild_id: GuildId,
+    scope: PlaylistScope,
+    name: &str,
+    requester: UserId,
+) -> SunnyResult<PlaylistSummary> {
+    let playlist = get_playlist(scope, name).await?;
+
+    let summary = PlaylistSummary {
+        added: playlist.tracks.len(),
+        duration: playlist.duration(),
+        truncated: false,
+    };
+
+    let tracks = playlist.tracks.into_iter().map(Metadata::from).collect();
+    queue::play_tracks(ctx, guild_id, tracks, Some(requester), EnqueueAt::Back).await?;
+
+    Ok(summary)
+}
+
+/// Names of every playlist of `scope`, sorted
+#[instrument]
+pub async fn list(scope: PlaylistScope) -> SunnyResult<Vec<String>> {
+    let mut names = storage::list(&scope.dir())
+        .await?
+        .iter()
+        .filter_map(|p| p.file_stem()?.to_str().map(str::to_string))
+        .collect::<Vec<_>>();
+
+    names.sort();
+
+    Ok(names)
+}
+
+#[instrument]
+pub async fn delete(scope: PlaylistScope, name: &str) -> SunnyResult<()> {
+    let playlist = get_playlist(scope, name).await?;
+
+    storage::remove(&scope.path(&playlist.name)).await
+}
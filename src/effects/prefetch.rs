@@ -0,0 +1,38 @@
+//! # Prefetch
+//! Resolves the songs coming up while the current one plays, so they start
+//! right away instead of waiting for youtube-dl once their turn comes.
+
+use serenity::{client::Context, model::id::GuildId};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    emit, sources,
+    utils::{SunnyError, SunnyResult},
+};
+
+/// Amount of songs after the current one which are resolved ahead of time
+const PREFETCHED_SONGS: usize = 2;
+
+/// Resolves the next few songs of the queue, and resolves them again if their
+/// stream urls are about to expire
+#[instrument(skip(ctx))]
+pub async fn prefetch_upcoming(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    // A song which can't be resolved now still gets its chance once it starts
+    for track in current_queue.iter().skip(1).take(PREFETCHED_SONGS) {
+        if let Some(url) = &track.metadata().source_url {
+            emit!(sources::prefetch(url).await, Level::WARN);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use serenity::{client::Context, model::id::GuildId};
+use tracing::instrument;
+
+use crate::{
+    sources::{self, Filter},
+    utils::{SunnyError, SunnyResult},
+};
+
+/// How far before its position the current track restarts.
+///
+/// Sources only restart when seeking backwards, seeking forward reads on from
+/// the ffmpeg which is already running.
+const RESTART_STEP: Duration = Duration::from_millis(100);
+
+/// Turns on `filter` for everything the guild plays, restarting the current
+/// track at the same position so the filter applies right away
+#[instrument(skip(ctx))]
+pub async fn set_filter(ctx: &Context, guild_id: GuildId, filter: Filter) -> SunnyResult<()> {
+    let current = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current();
+
+    sources::set_filter(guild_id, filter).await;
+
+    // Tracks which haven't started yet pick the filter up on their own
+    let track = match current {
+        Some(track) if track.is_seekable() => track,
+        _ => return Ok(()),
+    };
+
+    let position = track
+        .get_info()
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to get track info: {}", e).as_str()))?
+        .position;
+
+    // The source picks up at the same spot of the song, even if the speed changes
+    track
+        .seek_time(position.saturating_sub(RESTART_STEP))
+        .map_err(|e| {
+            SunnyError::user_and_log(
+                "Failed to apply the filter :person_shrugging:",
+                format!("Failed to restart track: {}", e).as_str(),
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Turns the filter off again, e.g. after leaving voice
+pub async fn reset_filter(guild_id: GuildId) {
+    sources::set_filter(guild_id, Filter::Off).await;
+}
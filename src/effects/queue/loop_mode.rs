@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fmt};
+
+use once_cell::sync::Lazy;
+use serenity::{client::Context, model::id::GuildId, prelude::Mutex};
+use tracing::instrument;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    #[default]
+    Off,
+    /// Keeps replaying the current track
+    Track,
+    /// Re-enqueues every finished track at the back of the queue
+    Queue,
+}
+
+impl fmt::Display for LoopMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::Track => write!(f, "\u{1f502} Track"),
+            Self::Queue => write!(f, "\u{1f501} Queue"),
+        }
+    }
+}
+
+static LOOP_MODES: Lazy<Mutex<HashMap<GuildId, LoopMode>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub async fn get_loop_mode(guild_id: GuildId) -> LoopMode {
+    LOOP_MODES
+        .lock()
+        .await
+        .get(&guild_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Forgets the loop mode of a guild, e.g. after leaving voice
+pub async fn reset_loop_mode(guild_id: GuildId) {
+    LOOP_MODES.lock().await.remove(&guild_id);
+}
+
+/// Switches between `mode` and [`LoopMode::Off`], returning the new mode
+#[instrument(skip(ctx))]
+pub async fn toggle_loop(
+    ctx: &Context,
+    guild_id: GuildId,
+    mode: LoopMode,
+) -> SunnyResult<LoopMode> {
+    let current = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current();
+
+    let new_mode = {
+        let mut modes = LOOP_MODES.lock().await;
+        let entry = modes.entry(guild_id).or_default();
+
+        *entry = if *entry == mode { LoopMode::Off } else { mode };
+        *entry
+    };
+
+    // New tracks are looped by `LoopTrackHandler` once they start playing
+    if let Some(track) = current {
+        let res = if new_mode == LoopMode::Track {
+            track.enable_loop()
+        } else {
+            track.disable_loop()
+        };
+
+        res.map_err(|e| {
+            SunnyError::user_and_log(
+                "Failed to change loop mode :person_shrugging:",
+                format!("Failed to change loop mode: {}", e).as_str(),
+            )
+        })?;
+    }
+
+    Ok(new_mode)
+}
@@ -1,20 +1,34 @@
 //! # Queue Effects
 //! These effects affect the queue in some way or another.
 
+mod filter;
+mod loop_mode;
+mod move_track;
 mod pause;
 mod play;
+mod remove;
 mod remove_at;
 mod resume;
+mod seek;
 mod shuffle;
 mod skip;
 mod stop;
 mod swap;
+mod volume;
+mod vote_skip;
 
+pub use filter::{reset_filter, set_filter};
+pub use loop_mode::{get_loop_mode, reset_loop_mode, toggle_loop, LoopMode};
+pub use move_track::move_track;
 pub use pause::pause;
-pub use play::{play, EnqueueAt};
+pub use play::{play, play_autoplayed, play_playlist, play_tracks, EnqueueAt, PlaylistSummary};
+pub use remove::{remove, RemoveFilter};
 pub use remove_at::remove_at;
 pub use resume::resume;
+pub use seek::{parse_duration, seek, SeekTarget};
 pub use shuffle::shuffle;
 pub use skip::skip;
 pub use stop::stop;
 pub use swap::swap;
+pub use volume::{set_volume, MAX_VOLUME};
+pub use vote_skip::{vote_skip, SkipVote};
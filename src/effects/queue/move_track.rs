@@ -0,0 +1,59 @@
+use std::cmp;
+
+use serenity::{client::Context, model::id::GuildId};
+use songbird::tracks::TrackHandle;
+use tracing::{event, instrument, Level};
+
+use crate::{
+    effects::{crossfade, persist},
+    emit,
+    utils::{SunnyError, SunnyResult},
+};
+
+/// Takes the track at `from` out of the queue and puts it back in at `to`
+#[instrument(skip(ctx))]
+pub async fn move_track(
+    ctx: &Context,
+    guild_id: GuildId,
+    from: usize,
+    to: usize,
+) -> SunnyResult<TrackHandle> {
+    if from == 0 || to == 0 {
+        return Err(SunnyError::user(
+            "A song index of 0 is invalid (The queue starts at 1)",
+        ));
+    }
+
+    let call_m = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?;
+
+    let call = call_m.lock().await;
+
+    let q = call.queue();
+
+    if cmp::max(from, to) >= q.len() {
+        return Err(SunnyError::user("Can't move non-existing index"));
+    }
+
+    let track = q.modify_queue(|q| {
+        // Both indices were checked above, so this is always `Some`
+        let track = q.remove(from)?;
+        let handle = track.handle();
+
+        q.insert(to, track);
+
+        Some(handle)
+    });
+
+    drop(call);
+
+    // The moved track might have been fading in, or moved in front of it
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
+    track.ok_or_else(|| SunnyError::log("Moved track disappeared"))
+}
@@ -5,7 +5,7 @@ use crate::utils::{SunnyError, SunnyResult};
 
 #[instrument(skip(ctx))]
 pub async fn pause(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
-    songbird::get(ctx)
+    let current_queue = songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
         .get(guild_id)
@@ -13,13 +13,21 @@ pub async fn pause(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
         .lock()
         .await
         .queue()
-        .current()
-        .ok_or_else(|| SunnyError::user("No track playing"))?
-        .pause()
-        .map_err(|e| {
+        .current_queue();
+
+    if current_queue.is_empty() {
+        return Err(SunnyError::user("No track playing"));
+    }
+
+    // The next song might be fading in already, the crossfade picks it up again on resume
+    for track in current_queue.iter().take(2) {
+        track.pause().map_err(|e| {
             SunnyError::user_and_log(
                 "Failed to pause :person_shrugging:",
                 format!("Failed to pause: {}", e).as_str(),
             )
-        })
+        })?;
+    }
+
+    Ok(())
 }
@@ -1,29 +1,199 @@
-use serenity::{client::Context, model::id::GuildId};
-use songbird::input::Restartable;
-use tracing::instrument;
+use std::{collections::HashMap, time::Duration};
 
-use crate::utils::{SunnyError, SunnyResult};
+use chrono::Utc;
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+};
+use songbird::{
+    create_player,
+    input::{Input, Metadata, Restartable},
+    Call,
+};
+use tracing::{event, instrument, Level};
 
-#[derive(Debug)]
+use crate::{
+    effects::{
+        config::{get_config, GuildConfig},
+        crossfade, format_total_duration, get_requester, get_song, loudness, persist, prefetch,
+    },
+    emit,
+    sources::{self, Timeline, MAX_PLAYLIST_ENTRIES},
+    structs::{Autoplayed, Requester, TrackTimeline},
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{get_loop_mode, LoopMode};
+
+#[derive(Clone, Copy, Debug)]
 pub enum EnqueueAt {
+    /// Right after the current song
     Front,
+    /// At the back, or at the requester's next turn if the guild has a fair queue
     Back,
+    /// At the very back, keeping the order of songs which were queued already
+    End,
 }
 
-#[instrument(skip(ctx))]
-pub async fn play(
+#[derive(Debug)]
+pub struct PlaylistSummary {
+    /// Amount of songs added to the queue
+    pub added: usize,
+    /// Combined duration of the added songs
+    pub duration: Duration,
+    /// Whether the playlist was cut off at [`MAX_PLAYLIST_ENTRIES`]
+    pub truncated: bool,
+}
+
+/// Finds where a song of `requester` goes so requesters take turns.
+///
+/// `requesters` holds who requested each song in the queue. Every song is in the
+/// round of how many songs its requester has before it, and the new song goes
+/// behind the last song of its round.
+fn fair_index(requesters: &[Option<UserId>], requester: Option<UserId>) -> usize {
+    let round = requesters.iter().filter(|r| **r == requester).count();
+    let mut counts = HashMap::new();
+
+    for (i, r) in requesters.iter().enumerate() {
+        let count = counts.entry(*r).or_insert(0);
+
+        // The current song keeps playing, so nothing goes in front of it
+        if i > 0 && *count > round {
+            return i;
+        }
+
+        *count += 1;
+    }
+
+    requesters.len()
+}
+
+/// Makes sure adding `inputs` for `requester` stays within the limits of `config`.
+///
+/// `requesters` holds who requested each song in the queue.
+fn check_limits(
+    config: &GuildConfig,
+    requesters: &[Option<UserId>],
+    inputs: &[Input],
+    requester: Option<UserId>,
+) -> SunnyResult<()> {
+    if let Some(max) = config.max_queue_length {
+        if requesters.len() + inputs.len() > max {
+            return Err(SunnyError::user(
+                format!("The queue is full, it fits at most {} songs", max).as_str(),
+            ));
+        }
+    }
+
+    if let (Some(max), Some(user_id)) = (config.max_user_songs, requester) {
+        let queued = requesters.iter().filter(|r| **r == Some(user_id)).count();
+
+        if queued + inputs.len() > max {
+            return Err(SunnyError::user(
+                format!(
+                    "You can have at most {} songs in the queue, you have {} already",
+                    max, queued
+                )
+                .as_str(),
+            ));
+        }
+    }
+
+    if let Some(max) = config.max_song_duration {
+        // Radio stations never end, the limit is about songs
+        let songs = inputs.iter().map(|i| &*i.metadata).filter(
+            |m| !matches!(&m.source_url, Some(url) if url.starts_with(sources::RADIO_PREFIX)),
+        );
+
+        for m in songs {
+            match m.duration {
+                Some(d) if d <= max => {}
+                Some(_) => {
+                    return Err(SunnyError::user(
+                        format!(
+                            "`{}` is too long, songs can be at most {}",
+                            get_song(m),
+                            format_total_duration(max)
+                        )
+                        .as_str(),
+                    ))
+                }
+                None => {
+                    return Err(SunnyError::user(
+                        format!(
+                            "Couldn't tell how long `{}` is, songs can be at most {}",
+                            get_song(m),
+                            format_total_duration(max)
+                        )
+                        .as_str(),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `input` to the queue, at `index` or the back if there is none
+async fn enqueue(
+    call: &mut Call,
+    input: Input,
+    timeline: Timeline,
+    index: Option<usize>,
+    volume: f32,
+    requester: Option<UserId>,
+    autoplayed: bool,
+) {
+    let (mut track, handle) = create_player(input);
+    track.set_volume(volume);
+
+    handle
+        .typemap()
+        .write()
+        .await
+        .insert::<TrackTimeline>(timeline);
+
+    if let Some(requester) = requester {
+        handle
+            .typemap()
+            .write()
+            .await
+            .insert::<Requester>(Requester {
+                user_id: requester,
+                enqueued_at: Utc::now(),
+            });
+    }
+
+    if autoplayed {
+        handle
+            .typemap()
+            .write()
+            .await
+            .insert::<Autoplayed>(Autoplayed);
+    }
+
+    call.enqueue(track);
+
+    if let Some(index) = index {
+        call.queue().modify_queue(|q| {
+            if let Some(track) = q.pop_back() {
+                q.insert(index.min(q.len()), track);
+            }
+        });
+    }
+}
+
+/// Adds `sources` to the queue of the current call in order, returning the position
+/// of the last one, 0 meaning it's playing now
+async fn enqueue_all(
     ctx: &Context,
     guild_id: GuildId,
-    url: String,
-    enqueu_at: EnqueueAt,
+    sources: Vec<(Restartable, Timeline)>,
+    requester: Option<UserId>,
+    enqueue_at: EnqueueAt,
+    autoplayed: bool,
 ) -> SunnyResult<usize> {
-    let source = Restartable::ytdl(url, true).await.map_err(|e| {
-        SunnyError::user_and_log(
-            "Error starting stream",
-            format!("Error sourcing ffmpeg {:?}", e).as_str(),
-        )
-    })?;
-
     let songbird = songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?;
@@ -32,21 +202,213 @@ pub async fn play(
         .get(guild_id)
         .ok_or_else(|| SunnyError::log("No Call"))?;
 
+    let config = get_config(guild_id).await;
+    let volume = f32::from(config.volume) / 100.0;
+
+    // The limits are checked against the metadata the sources looked up when they were created
+    let (inputs, timelines): (Vec<_>, Vec<_>) = sources
+        .into_iter()
+        .map(|(source, timeline)| (Input::from(source), timeline))
+        .unzip();
+
     let mut call = call_m.lock().await;
 
-    match enqueu_at {
-        EnqueueAt::Front => {
-            call.enqueue_source(source.into());
-            call.queue().modify_queue(|q| {
-                if let Some(track) = q.pop_back() {
-                    q.push_front(track);
-                    if q.len() > 1 {
-                        q.swap(0, 1);
-                    }
-                }
-            });
+    let was_empty = call.queue().is_empty();
+
+    let mut requesters = Vec::with_capacity(call.queue().len());
+    let mut position = 0;
+
+    for track in call.queue().current_queue() {
+        requesters.push(get_requester(&track).await.map(|r| r.user_id));
+    }
+
+    // Songs which were queued before made it past the limits already
+    if !matches!(enqueue_at, EnqueueAt::End) {
+        check_limits(&config, &requesters, &inputs, requester)?;
+    }
+
+    for (i, (input, timeline)) in inputs.into_iter().zip(timelines).enumerate() {
+        let index = match enqueue_at {
+            EnqueueAt::Front => Some(1 + i),
+            EnqueueAt::Back if config.fair_queue => {
+                let index = fair_index(&requesters, requester);
+                requesters.insert(index, requester);
+
+                Some(index)
+            }
+            EnqueueAt::Back | EnqueueAt::End => None,
+        };
+
+        enqueue(
+            &mut call, input, timeline, index, volume, requester, autoplayed,
+        )
+        .await;
+
+        let last = call.queue().len() - 1;
+        position = index.map_or(last, |i| i.min(last));
+    }
+
+    // The first track doesn't fire a play event, so `LoopTrackHandler` won't see it
+    if was_empty && get_loop_mode(guild_id).await == LoopMode::Track {
+        if let Some(track) = call.queue().current() {
+            track
+                .enable_loop()
+                .map_err(|e| SunnyError::log(format!("Failed to loop track: {}", e).as_str()))?;
         }
-        EnqueueAt::Back => call.enqueue_source(source.into()),
+    }
+
+    drop(call);
+
+    // Songs queued in front of the one fading in push it back
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
+    // The new songs might be up next
+    {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            let res = prefetch::prefetch_upcoming(&ctx, guild_id).await;
+
+            emit!(res, Level::WARN);
+        });
+    }
+
+    // The first track doesn't fire a play event and the next one should be ready in time
+    if config.normalize {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            let res = loudness::normalize_upcoming(&ctx, guild_id).await;
+
+            emit!(res, Level::WARN);
+        });
+    }
+
+    Ok(position)
+}
+
+/// Looks up the song behind `url` and adds it to the queue, returning its position
+#[instrument(skip(ctx))]
+pub async fn play(
+    ctx: &Context,
+    guild_id: GuildId,
+    url: String,
+    requester: Option<UserId>,
+    enqueu_at: EnqueueAt,
+) -> SunnyResult<usize> {
+    let source = sources::source(url, None, guild_id).await?;
+
+    enqueue_all(ctx, guild_id, vec![source], requester, enqueu_at, false).await
+}
+
+/// Sources a song of which the metadata is already known.
+///
+/// Guilds with a `limited` song duration can't check songs without one, so
+/// those are looked up by the source first.
+async fn known_source(
+    m: Metadata,
+    guild_id: GuildId,
+    limited: bool,
+) -> SunnyResult<(Restartable, Timeline)> {
+    let url = m
+        .source_url
+        .clone()
+        .ok_or_else(|| SunnyError::log("Track has no source url"))?;
+
+    let m = Some(m).filter(|m| m.duration.is_some() || !limited);
+
+    sources::source(url, m, guild_id).await
+}
+
+/// Adds songs of which the metadata is already known, returning the position of the last one.
+///
+/// The songs are only sourced once they are about to play.
+#[instrument(skip(ctx, tracks))]
+pub async fn play_tracks(
+    ctx: &Context,
+    guild_id: GuildId,
+    tracks: Vec<Metadata>,
+    requester: Option<UserId>,
+    enqueue_at: EnqueueAt,
+) -> SunnyResult<usize> {
+    let limited = get_config(guild_id).await.max_song_duration.is_some();
+    let mut sources = Vec::with_capacity(tracks.len());
+
+    for m in tracks {
+        sources.push(known_source(m, guild_id, limited).await?);
+    }
+
+    enqueue_all(ctx, guild_id, sources, requester, enqueue_at, false).await
+}
+
+/// Adds a song Sunny picked herself, tagged as [`Autoplayed`]
+#[instrument(skip(ctx))]
+pub async fn play_autoplayed(ctx: &Context, guild_id: GuildId, m: Metadata) -> SunnyResult<usize> {
+    let limited = get_config(guild_id).await.max_song_duration.is_some();
+    let source = known_source(m, guild_id, limited).await?;
+
+    enqueue_all(ctx, guild_id, vec![source], None, EnqueueAt::Back, true).await
+}
+
+/// Adds every entry of the playlist behind `url` as its own song
+#[instrument(skip(ctx))]
+pub async fn play_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    url: String,
+    requester: Option<UserId>,
+    enqueue_at: EnqueueAt,
+) -> SunnyResult<PlaylistSummary> {
+    let playlist = sources::list_playlist(&url, *MAX_PLAYLIST_ENTRIES).await?;
+
+    let summary = PlaylistSummary {
+        added: playlist.entries.len(),
+        duration: playlist.entries.iter().filter_map(|m| m.duration).sum(),
+        truncated: playlist.truncated,
     };
-    Ok(call.queue().len())
+
+    play_tracks(ctx, guild_id, playlist.entries, requester, enqueue_at).await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Option<UserId> = Some(UserId(1));
+    const B: Option<UserId> = Some(UserId(2));
+    const C: Option<UserId> = Some(UserId(3));
+
+    #[test]
+    fn empty_queue_plays_right_away() {
+        assert_eq!(fair_index(&[], A), 0);
+    }
+
+    #[test]
+    fn new_requester_goes_after_the_current_song() {
+        assert_eq!(fair_index(&[A, A, A], B), 1);
+    }
+
+    #[test]
+    fn requesters_take_turns() {
+        // A A B A + B => A A B B A
+        assert_eq!(fair_index(&[A, A, B, A], B), 3);
+        // A B A B + C => A B C A B
+        assert_eq!(fair_index(&[A, B, A, B], C), 2);
+        // A B C + A => A B C A
+        assert_eq!(fair_index(&[A, B, C], A), 3);
+    }
+
+    #[test]
+    fn goes_to_the_back_when_every_round_is_full() {
+        assert_eq!(fair_index(&[A, B, A, B], A), 4);
+        assert_eq!(fair_index(&[A], A), 1);
+    }
+
+    #[test]
+    fn songs_without_requester_take_turns_too() {
+        // A - A A + - => A - A - A
+        assert_eq!(fair_index(&[A, None, A, A], None), 3);
+    }
 }
@@ -0,0 +1,227 @@
+use std::{collections::HashSet, str::FromStr};
+
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    utils::parse_username,
+};
+use songbird::tracks::{Queued, TrackHandle};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    effects::{crossfade, get_requester, persist},
+    emit,
+    utils::{SunnyError, SunnyResult},
+};
+
+/// Titles youtube-dl gives playlist entries which can't be played anymore
+const UNAVAILABLE_TITLES: [&str; 2] = ["[Deleted video]", "[Private video]"];
+
+/// Which songs to remove from the queue, the current one is always kept
+#[derive(Clone, Copy, Debug)]
+pub enum RemoveFilter {
+    /// Positions from and to, both inclusive
+    Range(usize, usize),
+    /// Everything a user requested
+    User(UserId),
+    /// Every song which is in the queue before already
+    Duplicates,
+    /// Songs which can't be played
+    Unavailable,
+    All,
+}
+
+impl FromStr for RemoveFilter {
+    type Err = SunnyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || {
+            SunnyError::user(
+                "Try a position, a range like `3-9`, a user, `duplicates` or `unavailable`",
+            )
+        };
+
+        match s {
+            "duplicates" | "dupes" => return Ok(Self::Duplicates),
+            "unavailable" => return Ok(Self::Unavailable),
+            "all" => return Ok(Self::All),
+            _ => {}
+        }
+
+        if let Some(user_id) = parse_username(s) {
+            return Ok(Self::User(UserId(user_id)));
+        }
+
+        let (from, to) = s.split_once('-').unwrap_or((s, s));
+        let from = from.trim().parse::<usize>().map_err(|_| invalid())?;
+        let to = to.trim().parse::<usize>().map_err(|_| invalid())?;
+
+        if from == 0 {
+            return Err(SunnyError::user(
+                "A song index of 0 is invalid (The queue starts at 1)",
+            ));
+        }
+
+        if from > to {
+            return Err(invalid());
+        }
+
+        Ok(Self::Range(from, to))
+    }
+}
+
+fn is_unavailable(track: &TrackHandle) -> bool {
+    let m = track.metadata();
+
+    m.source_url.is_none()
+        || matches!(m.title.as_deref(), Some(t) if UNAVAILABLE_TITLES.contains(&t))
+}
+
+/// Removes every song matching `filter`, returning the removed songs
+#[instrument(skip(ctx))]
+pub async fn remove(
+    ctx: &Context,
+    guild_id: GuildId,
+    filter: RemoveFilter,
+) -> SunnyResult<Vec<TrackHandle>> {
+    let call_m = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?;
+
+    let call = call_m.lock().await;
+    let queue = call.queue();
+
+    // Requesters live behind an async lock, so they can't be read while modifying the queue
+    let mut requesters = Vec::new();
+
+    if let RemoveFilter::User(_) = filter {
+        for track in queue.current_queue() {
+            requesters.push(get_requester(&track).await.map(|r| r.user_id));
+        }
+    }
+
+    let removed = queue.modify_queue(|q| {
+        let mut seen = HashSet::new();
+        let mut keep = Vec::with_capacity(q.len());
+
+        for (i, track) in q.iter().enumerate() {
+            let remove = i > 0
+                && match filter {
+                    RemoveFilter::Range(from, to) => (from..=to).contains(&i),
+                    RemoveFilter::User(user_id) => {
+                        requesters.get(i).copied().flatten() == Some(user_id)
+                    }
+                    RemoveFilter::Duplicates => {
+                        matches!(&track.metadata().source_url, Some(url) if seen.contains(url))
+                    }
+                    RemoveFilter::Unavailable => is_unavailable(track),
+                    RemoveFilter::All => true,
+                };
+
+            if let Some(url) = &track.metadata().source_url {
+                seen.insert(url.clone());
+            }
+
+            keep.push(!remove);
+        }
+
+        let mut removed = Vec::new();
+        let tracks = q.drain(..).collect::<Vec<_>>();
+
+        for (track, keep) in tracks.into_iter().zip(keep) {
+            if keep {
+                q.push_back(track);
+            } else {
+                removed.push(track);
+            }
+        }
+
+        removed
+    });
+
+    drop(call);
+
+    if removed.is_empty() {
+        return Err(SunnyError::user("Nothing to remove..."));
+    }
+
+    // Removed tracks are still loaded in the driver until they are stopped
+    let handles = removed.iter().map(Queued::handle).collect::<Vec<_>>();
+    for handle in &handles {
+        emit!(handle.stop(), Level::WARN);
+    }
+
+    // Removing songs in front of the one fading in moves it up
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
+    Ok(handles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<RemoveFilter> {
+        s.parse().ok()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert!(matches!(parse("3-9"), Some(RemoveFilter::Range(3, 9))));
+        assert!(matches!(parse(" 3 - 9 "), Some(RemoveFilter::Range(3, 9))));
+        assert!(matches!(parse("4"), Some(RemoveFilter::Range(4, 4))));
+        assert!(matches!(parse("5-5"), Some(RemoveFilter::Range(5, 5))));
+    }
+
+    #[test]
+    fn rejects_backwards_ranges() {
+        assert!(parse("9-3").is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_bounds() {
+        for s in [
+            "0",
+            "0-3",
+            "-3",
+            "3-",
+            "3-9-12",
+            "99999999999999999999999",
+            "1-99999999999999999999999",
+        ] {
+            assert!(parse(s).is_none(), "{:?} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn parses_keywords() {
+        assert!(matches!(
+            parse("duplicates"),
+            Some(RemoveFilter::Duplicates)
+        ));
+        assert!(matches!(parse("dupes"), Some(RemoveFilter::Duplicates)));
+        assert!(matches!(
+            parse("unavailable"),
+            Some(RemoveFilter::Unavailable)
+        ));
+        assert!(matches!(parse("all"), Some(RemoveFilter::All)));
+        assert!(parse("everything").is_none());
+    }
+
+    #[test]
+    fn parses_users() {
+        assert!(matches!(
+            parse("<@123456789>"),
+            Some(RemoveFilter::User(UserId(123_456_789)))
+        ));
+        assert!(matches!(
+            parse("<@!123456789>"),
+            Some(RemoveFilter::User(UserId(123_456_789)))
+        ));
+    }
+}
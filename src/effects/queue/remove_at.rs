@@ -2,13 +2,17 @@ use std::num::NonZeroUsize;
 
 use serenity::{client::Context, model::id::GuildId};
 use songbird::tracks::Queued;
-use tracing::instrument;
+use tracing::{event, instrument, Level};
 
-use crate::utils::{SunnyError, SunnyResult};
+use crate::{
+    effects::{crossfade, persist},
+    emit,
+    utils::{SunnyError, SunnyResult},
+};
 
 #[instrument(skip(ctx))]
 pub async fn remove_at(ctx: &Context, guild_id: GuildId, at: NonZeroUsize) -> SunnyResult<Queued> {
-    songbird::get(ctx)
+    let removed = songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
         .get(guild_id)
@@ -17,5 +21,13 @@ pub async fn remove_at(ctx: &Context, guild_id: GuildId, at: NonZeroUsize) -> Su
         .await
         .queue()
         .dequeue(at.into())
-        .ok_or_else(|| SunnyError::user("Nothing to remove..."))
+        .ok_or_else(|| SunnyError::user("Nothing to remove..."))?;
+
+    // The removed track is still loaded in the driver until it is stopped
+    emit!(removed.stop(), Level::WARN);
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
+    Ok(removed)
 }
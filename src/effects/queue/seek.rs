@@ -0,0 +1,231 @@
+use std::{str::FromStr, time::Duration};
+
+use serenity::{client::Context, model::id::GuildId};
+use tracing::instrument;
+
+use crate::{
+    effects::{format_duration, get_timeline},
+    utils::{SunnyError, SunnyResult},
+};
+
+const INVALID_TIMESTAMP: &str =
+    "Invalid timestamp, try something like `1:30`, `1:02:03`, `+30s` or `-1m`";
+
+#[derive(Clone, Copy, Debug)]
+pub enum SeekTarget {
+    To(Duration),
+    Forward(Duration),
+    Backward(Duration),
+}
+
+impl FromStr for SeekTarget {
+    type Err = SunnyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(s) = s.strip_prefix('+') {
+            parse_duration(s).map(Self::Forward)
+        } else if let Some(s) = s.strip_prefix('-') {
+            parse_duration(s).map(Self::Backward)
+        } else {
+            parse_duration(s).map(Self::To)
+        }
+    }
+}
+
+impl SeekTarget {
+    /// Where seeking from `position` ends up, not going before the start
+    fn resolve(self, position: Duration) -> SunnyResult<Duration> {
+        match self {
+            Self::To(d) => Ok(d),
+            Self::Forward(d) => position
+                .checked_add(d)
+                .ok_or_else(|| SunnyError::user(INVALID_TIMESTAMP)),
+            Self::Backward(d) => Ok(position.saturating_sub(d)),
+        }
+    }
+}
+
+/// Parses either a `[hh:]mm:ss` timestamp or an amount like `1m30s`.
+/// A bare number is taken as seconds.
+pub fn parse_duration(s: &str) -> SunnyResult<Duration> {
+    let invalid = || SunnyError::user(INVALID_TIMESTAMP);
+
+    if s.contains(':') {
+        let parts = s
+            .split(':')
+            .map(str::parse::<u64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+
+        let secs = match parts[..] {
+            [m, s] if s < 60 => m.checked_mul(60).and_then(|m| m.checked_add(s)),
+            [h, m, s] if m < 60 && s < 60 => {
+                h.checked_mul(3600).and_then(|h| h.checked_add(m * 60 + s))
+            }
+            _ => None,
+        };
+
+        return secs.map(Duration::from_secs).ok_or_else(invalid);
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+
+        let n: u64 = number.parse().map_err(|_| invalid())?;
+        secs = n
+            .checked_mul(unit)
+            .and_then(|n| secs.checked_add(n))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    // Trailing number without a unit, e.g. `90`
+    if !number.is_empty() {
+        let n: u64 = number.parse().map_err(|_| invalid())?;
+        secs = secs.checked_add(n).ok_or_else(invalid)?;
+    } else if s.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(secs))
+}
+
+/// Moves the playhead of the current track, returning the new position
+#[instrument(skip(ctx))]
+pub async fn seek(ctx: &Context, guild_id: GuildId, target: SeekTarget) -> SunnyResult<Duration> {
+    let track = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or_else(|| SunnyError::user("No track playing"))?;
+
+    if !track.is_seekable() {
+        return Err(SunnyError::user("This track doesn't support seeking"));
+    }
+
+    let position = track
+        .get_info()
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to get track info: {}", e).as_str()))?
+        .position;
+
+    // Timestamps are in the song, which speed presets play faster or slower
+    let timeline = get_timeline(&track).await;
+    let position = timeline.song_position(position);
+
+    let new_position = target.resolve(position)?;
+
+    if let Some(duration) = track.metadata().duration {
+        if new_position >= duration {
+            return Err(SunnyError::user(
+                format!(
+                    "Can't seek past the end of the song ({})",
+                    format_duration(duration)
+                )
+                .as_str(),
+            ));
+        }
+    }
+
+    track
+        .seek_time(timeline.position(new_position))
+        .map_err(|e| {
+            SunnyError::user_and_log(
+                "Failed to seek :person_shrugging:",
+                format!("Failed to seek: {}", e).as_str(),
+            )
+        })?;
+
+    Ok(new_position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: &str) -> Option<u64> {
+        parse_duration(s).ok().map(|d| d.as_secs())
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(secs("1:30"), Some(90));
+        assert_eq!(secs("01:02:03"), Some(3723));
+        assert_eq!(secs("0:00"), Some(0));
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(secs("90"), Some(90));
+        assert_eq!(secs("1m30s"), Some(90));
+        assert_eq!(secs("1h"), Some(3600));
+        assert_eq!(secs("2m5"), Some(125));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for s in [
+            "", "1:60", "1:60:00", "1:2:3:4", "1::2", "abc", "1x", "m", "-1:00",
+        ] {
+            assert_eq!(secs(s), None, "{:?} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(secs(&format!("{}:00:00", u64::MAX / 3600 + 1)), None);
+        assert_eq!(secs(&format!("{}:00", u64::MAX / 60 + 1)), None);
+        assert_eq!(secs(&format!("{}h", u64::MAX / 3600 + 1)), None);
+        assert_eq!(secs(&format!("{}h{}s", u64::MAX / 3600, u64::MAX)), None);
+        assert_eq!(secs("99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn parses_targets() {
+        assert!(matches!("+30s".parse(), Ok(SeekTarget::Forward(d)) if d.as_secs() == 30));
+        assert!(matches!("-1m".parse(), Ok(SeekTarget::Backward(d)) if d.as_secs() == 60));
+        assert!(matches!(" 1:30 ".parse(), Ok(SeekTarget::To(d)) if d.as_secs() == 90));
+        assert!("--1m".parse::<SeekTarget>().is_err());
+    }
+
+    #[test]
+    fn seeking_back_stops_at_the_start() {
+        let target = SeekTarget::Backward(Duration::from_secs(60));
+
+        assert_eq!(
+            target.resolve(Duration::from_secs(30)).ok(),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            target.resolve(Duration::from_secs(90)).ok(),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn seeking_forward_rejects_overflow() {
+        let target = SeekTarget::Forward(Duration::MAX);
+
+        assert!(target.resolve(Duration::from_secs(1)).is_err());
+    }
+}
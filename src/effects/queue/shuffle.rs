@@ -2,9 +2,13 @@ use std::collections::VecDeque;
 
 use rand::{rngs::SmallRng, SeedableRng};
 use serenity::{client::Context, model::id::GuildId};
-use tracing::instrument;
+use tracing::{event, instrument, Level};
 
-use crate::utils::{SunnyError, SunnyResult};
+use crate::{
+    effects::{crossfade, persist},
+    emit,
+    utils::{SunnyError, SunnyResult},
+};
 
 /// Shuffles a `VecDeque` except element 0, why? implementation details
 fn shuffle_vdq<T, R>(values: &mut VecDeque<T>, mut rng: R)
@@ -35,5 +39,9 @@ pub async fn shuffle(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
             shuffle_vdq(q, rng);
         });
 
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
     Ok(())
 }
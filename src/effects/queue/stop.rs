@@ -3,8 +3,13 @@ use tracing::instrument;
 
 use crate::utils::{SunnyError, SunnyResult};
 
+use super::reset_loop_mode;
+
 #[instrument(skip(ctx))]
 pub async fn stop(ctx: &Context, guild_id: GuildId) -> SunnyResult<()> {
+    // Otherwise the stopped tracks get looped right back into the queue
+    reset_loop_mode(guild_id).await;
+
     songbird::get(ctx)
         .await
         .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
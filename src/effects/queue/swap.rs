@@ -2,9 +2,13 @@ use std::cmp;
 
 use serenity::{client::Context, model::id::GuildId};
 use songbird::tracks::TrackHandle;
-use tracing::instrument;
+use tracing::{event, instrument, Level};
 
-use crate::utils::{SunnyError, SunnyResult};
+use crate::{
+    effects::{crossfade, persist},
+    emit,
+    utils::{SunnyError, SunnyResult},
+};
 
 #[instrument(skip(ctx))]
 pub async fn swap(
@@ -39,5 +43,12 @@ pub async fn swap(
         (q[a].clone(), q[b].clone())
     });
 
+    drop(call);
+
+    // Either track might have been fading in
+    emit!(crossfade::cancel_fade_in(ctx, guild_id).await, Level::WARN);
+
+    persist::save_queue(ctx, guild_id).await;
+
     Ok((t1, t2))
 }
@@ -0,0 +1,51 @@
+use serenity::{client::Context, model::id::GuildId};
+use tracing::instrument;
+
+use crate::{
+    effects::{config::update_config, loudness::get_gain},
+    utils::{SunnyError, SunnyResult},
+};
+
+/// Highest volume in percent, anything louder mostly adds distortion
+pub const MAX_VOLUME: u16 = 200;
+
+/// Sets the volume of every track in the queue and remembers it for later tracks
+#[instrument(skip(ctx))]
+pub async fn set_volume(ctx: &Context, guild_id: GuildId, volume: u16) -> SunnyResult<()> {
+    if volume > MAX_VOLUME {
+        return Err(SunnyError::user(
+            format!("The volume goes from 0 to {}%", MAX_VOLUME).as_str(),
+        ));
+    }
+
+    update_config(guild_id, |c| {
+        c.volume = volume;
+        Ok(())
+    })
+    .await?;
+
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::log("No Call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    for track in current_queue {
+        let gain = get_gain(&track).await;
+
+        track
+            .set_volume(f32::from(volume) / 100.0 * gain)
+            .map_err(|e| {
+                SunnyError::user_and_log(
+                    "Failed to change the volume :person_shrugging:",
+                    format!("Failed to set volume: {}", e).as_str(),
+                )
+            })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::Mutex,
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    checks::has_dj_role,
+    effects::{config::get_config, get_requester},
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::skip;
+
+pub enum SkipVote {
+    /// The track got skipped, holds the remaining queue length
+    Skipped(usize),
+    /// Not enough listeners voted yet
+    Voted { votes: usize, needed: usize },
+}
+
+struct Votes {
+    /// The track which is being voted on
+    track: Uuid,
+    voters: HashSet<UserId>,
+}
+
+static VOTES: Lazy<Mutex<HashMap<GuildId, Votes>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Skips the current track, or adds a vote to skip it if the guild uses vote skipping.
+///
+/// The requester of the track and DJs skip right away.
+#[instrument(skip(ctx))]
+pub async fn vote_skip(ctx: &Context, guild_id: GuildId, user_id: UserId) -> SunnyResult<SkipVote> {
+    let config = get_config(guild_id).await;
+
+    if !config.vote_skip {
+        return skip(ctx, guild_id).await.map(SkipVote::Skipped);
+    }
+
+    let (track, channel) = {
+        let call_m = songbird::get(ctx)
+            .await
+            .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+            .get(guild_id)
+            .ok_or_else(|| SunnyError::log("No Call"))?;
+
+        let call = call_m.lock().await;
+
+        (
+            call.queue()
+                .current()
+                .ok_or_else(|| SunnyError::user("No track playing"))?,
+            call.current_channel()
+                .ok_or_else(|| SunnyError::log("Couldn't find songbird channel"))?,
+        )
+    };
+
+    if get_requester(&track).await.map(|r| r.user_id) == Some(user_id)
+        || has_dj_role(ctx, guild_id, user_id).await?
+    {
+        VOTES.lock().await.remove(&guild_id);
+        return skip(ctx, guild_id).await.map(SkipVote::Skipped);
+    }
+
+    let guild = ctx
+        .cache
+        .guild(guild_id)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get guild"))?;
+
+    // Everyone but bots in the voice channel
+    let listeners = guild
+        .voice_states
+        .values()
+        .filter(|vs| vs.channel_id.map(|c| c.0) == Some(channel.0))
+        .filter(|vs| guild.members.get(&vs.user_id).map(|m| m.user.bot) != Some(true))
+        .map(|vs| vs.user_id)
+        .collect::<HashSet<_>>();
+
+    let ratio = f32::from(config.vote_skip_ratio) / 100.0;
+    let needed = ((listeners.len() as f32 * ratio).ceil() as usize).max(1);
+
+    let votes = {
+        let mut votes = VOTES.lock().await;
+        let entry = votes.entry(guild_id).or_insert_with(|| Votes {
+            track: track.uuid(),
+            voters: HashSet::new(),
+        });
+
+        // Votes for an earlier track don't count
+        if entry.track != track.uuid() {
+            entry.track = track.uuid();
+            entry.voters.clear();
+        }
+
+        entry.voters.insert(user_id);
+
+        // Listeners who left since voting don't count either
+        entry.voters.intersection(&listeners).count()
+    };
+
+    if votes < needed {
+        return Ok(SkipVote::Voted { votes, needed });
+    }
+
+    VOTES.lock().await.remove(&guild_id);
+    skip(ctx, guild_id).await.map(SkipVote::Skipped)
+}
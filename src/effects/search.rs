@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use serenity::{
+    builder::{CreateActionRow, CreateEmbed},
+    client::Context,
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        interactions::{message_component::ButtonStyle, InteractionResponseType},
+    },
+};
+use songbird::input::Metadata;
+use tracing::instrument;
+
+use crate::{
+    sources,
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::{
+    format_duration, get_artist, get_song, get_title,
+    queue::{self, EnqueueAt},
+};
+
+/// Amount of results shown to pick from
+const RESULTS: usize = 5;
+const RESULT_ID_PREFIX: &str = "search_";
+
+fn generate_embed(terms: &str, results: &[Metadata]) -> CreateEmbed {
+    let lines = results
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            format!(
+                "**{}.** {} - {} [{}]",
+                i + 1,
+                get_title(m),
+                get_artist(m),
+                format_duration(m.duration.unwrap_or_default())
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut e = CreateEmbed::default();
+    e.author(|a| a.name(format!("Results for \"{}\"", terms)));
+    e.description(lines.join("\n"));
+    e.footer(|f| f.text("Pick a song to add it to the queue"));
+
+    e
+}
+
+fn build_action_row(amount: usize) -> CreateActionRow {
+    let mut row = CreateActionRow::default();
+
+    for i in 0..amount {
+        row.create_button(|b| {
+            b.style(ButtonStyle::Primary);
+            b.label(i + 1);
+            b.custom_id(format!("{}{}", RESULT_ID_PREFIX, i))
+        });
+    }
+
+    row
+}
+
+/// Sends the top results for `terms` and adds the one `user_id` picks to the queue
+#[instrument(skip(ctx), name = "search_embed")]
+pub async fn send_embed(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    terms: &str,
+) -> SunnyResult<()> {
+    let results = sources::search(terms, RESULTS).await?;
+
+    let mut message = channel_id
+        .send_message(&ctx.http, |m| {
+            m.components(|c| c.set_action_rows(vec![build_action_row(results.len())]));
+            m.set_embed(generate_embed(terms, &results))
+        })
+        .await
+        .map_err(|e| SunnyError::log(format!("Unable to send search message: {:?}", e).as_str()))?;
+
+    let mci = message
+        .await_component_interaction(&ctx.shard)
+        .author_id(user_id)
+        .timeout(Duration::from_secs(60))
+        .await;
+
+    let picked = mci.as_ref().and_then(|mci| {
+        mci.data
+            .custom_id
+            .strip_prefix(RESULT_ID_PREFIX)
+            .and_then(|i| i.parse::<usize>().ok())
+            .and_then(|i| results.get(i))
+    });
+
+    let content = match (&mci, picked) {
+        (Some(mci), Some(m)) => {
+            mci.create_interaction_response(&ctx.http, |cir| {
+                cir.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await
+            .map_err(|e| {
+                SunnyError::log(format!("Unable to create interaction response: {:?}", e).as_str())
+            })?;
+
+            let url = m
+                .source_url
+                .clone()
+                .ok_or_else(|| SunnyError::log("Search result has no source url"))?;
+
+            queue::play(ctx, guild_id, url, Some(user_id), EnqueueAt::Back).await?;
+
+            format!("Added {} to the queue", get_song(m))
+        }
+        _ => "Search timed out".to_string(),
+    };
+
+    // Remove buttons once a song was picked or nobody did
+    message
+        .edit(&ctx.http, |e| {
+            e.content(content);
+            e.components(|c| c)
+        })
+        .await
+        .map_err(|e| SunnyError::log(format!("Unable clear buttons {:?}", e).as_str()))?;
+
+    Ok(())
+}
@@ -0,0 +1,368 @@
+//! # Transfer
+//! Exports the queue as a file and imports such files again, as either an M3U
+//! playlist or JSON.
+
+use std::{path::Path, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+};
+use songbird::input::Metadata;
+use tracing::instrument;
+use url::Url;
+
+use crate::{
+    sources::{self, MAX_PLAYLIST_ENTRIES},
+    utils::{SunnyError, SunnyResult},
+};
+
+use super::queue::{self, EnqueueAt, PlaylistSummary};
+
+/// Largest file `import` downloads, in bytes
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    M3u,
+    Json,
+}
+
+impl Format {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::M3u => "m3u",
+            Self::Json => "json",
+        }
+    }
+
+    /// Picks the format from the extension of `filename`
+    pub fn from_filename(filename: &str) -> SunnyResult<Self> {
+        Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .parse()
+    }
+}
+
+impl FromStr for Format {
+    type Err = SunnyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "m3u" | "m3u8" => Ok(Self::M3u),
+            "json" => Ok(Self::Json),
+            _ => Err(SunnyError::user("Only `m3u` and `json` files work")),
+        }
+    }
+}
+
+/// A queue entry the way it's written to JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    source_url: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    artist: Option<String>,
+    /// In seconds
+    #[serde(default)]
+    duration: Option<u64>,
+}
+
+impl Entry {
+    fn from_metadata(m: &Metadata) -> Option<Self> {
+        Some(Self {
+            source_url: m.source_url.clone()?,
+            title: m.track.clone().or_else(|| m.title.clone()),
+            artist: m.artist.clone().or_else(|| m.channel.clone()),
+            duration: m.duration.map(|d| d.as_secs()),
+        })
+    }
+}
+
+impl From<Entry> for Metadata {
+    /// Durations of a file can't be trusted, the sources look them up themselves
+    fn from(e: Entry) -> Self {
+        Self {
+            title: e.title,
+            artist: e.artist,
+            source_url: Some(e.source_url),
+            channels: Some(2),
+            ..Self::default()
+        }
+    }
+}
+
+fn write_m3u(entries: &[Entry]) -> String {
+    let mut m3u = "#EXTM3U\n".to_string();
+
+    for e in entries {
+        let duration = e.duration.map_or(-1, |d| d as i64);
+        let info = match (&e.artist, &e.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            (_, None) => String::new(),
+        };
+
+        m3u.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            duration, info, e.source_url
+        ));
+    }
+
+    m3u
+}
+
+fn read_m3u(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    // The #EXTINF line of the next url
+    let mut info = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            info = extinf
+                .split_once(',')
+                .map(|(d, i)| (d.to_string(), i.to_string()));
+            continue;
+        }
+
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (duration, info) = info.take().unwrap_or_default();
+        let (artist, title) = match info.split_once(" - ") {
+            Some((artist, title)) => (Some(artist.to_string()), Some(title.to_string())),
+            None if info.is_empty() => (None, None),
+            None => (None, Some(info)),
+        };
+
+        entries.push(Entry {
+            source_url: line.to_string(),
+            title,
+            artist,
+            duration: duration.trim().parse().ok(),
+        });
+    }
+
+    entries
+}
+
+/// Writes the queue of `guild_id` in `format`, returning the file and the amount of songs in it
+#[instrument(skip(ctx))]
+pub async fn export(
+    ctx: &Context,
+    guild_id: GuildId,
+    format: Format,
+) -> SunnyResult<(Vec<u8>, usize)> {
+    let current_queue = songbird::get(ctx)
+        .await
+        .ok_or_else(|| SunnyError::log("Couldn't get songbird"))?
+        .get(guild_id)
+        .ok_or_else(|| SunnyError::user("Not currently in a call"))?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+
+    let entries = current_queue
+        .iter()
+        .filter_map(|t| Entry::from_metadata(t.metadata()))
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        return Err(SunnyError::user("Nothing in the queue to export"));
+    }
+
+    let file = match format {
+        Format::M3u => write_m3u(&entries).into_bytes(),
+        Format::Json => serde_json::to_vec_pretty(&entries)
+            .map_err(|e| SunnyError::log(format!("Failed to serialize: {}", e).as_str()))?,
+    };
+
+    Ok((file, entries.len()))
+}
+
+/// Checks `url` of an imported song, which is either a web url or one of the
+/// `local:` and `radio:` urls `export` writes. Their sources check those just
+/// like when they're played by command.
+fn import_url(url: &str) -> Option<String> {
+    let url = sources::parse_url(url)?;
+
+    if url.starts_with(sources::LOCAL_PREFIX) {
+        return Some(url);
+    }
+
+    let web = url.strip_prefix(sources::RADIO_PREFIX).unwrap_or(&url);
+
+    match Url::parse(web).ok()?.scheme() {
+        "http" | "https" => Some(url),
+        _ => None,
+    }
+}
+
+/// Reads the songs of an exported file, holding them to the same rules as songs added by url
+fn read_file(format: Format, file: &[u8]) -> SunnyResult<Vec<Metadata>> {
+    let text = std::str::from_utf8(file).map_err(|_| SunnyError::user("That file isn't text"))?;
+
+    let entries = match format {
+        Format::M3u => read_m3u(text),
+        Format::Json => serde_json::from_str::<Vec<Entry>>(text).map_err(|e| {
+            SunnyError::user(format!("That isn't an exported queue: {}", e).as_str())
+        })?,
+    };
+
+    if entries.is_empty() {
+        return Err(SunnyError::user("That file has no songs in it"));
+    }
+
+    if entries.len() > *MAX_PLAYLIST_ENTRIES {
+        return Err(SunnyError::user(
+            format!(
+                "That file has {} songs, imports are capped at {}",
+                entries.len(),
+                *MAX_PLAYLIST_ENTRIES
+            )
+            .as_str(),
+        ));
+    }
+
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut e)| {
+            e.source_url = import_url(&e.source_url).ok_or_else(|| {
+                SunnyError::user(format!("Song {} doesn't have a valid url", i + 1).as_str())
+            })?;
+
+            Ok(e.into())
+        })
+        .collect()
+}
+
+/// Adds every song of an exported `file` to the queue
+#[instrument(skip(ctx, file))]
+pub async fn import(
+    ctx: &Context,
+    guild_id: GuildId,
+    format: Format,
+    file: &[u8],
+    requester: UserId,
+) -> SunnyResult<PlaylistSummary> {
+    let tracks = read_file(format, file)?;
+
+    let summary = PlaylistSummary {
+        added: tracks.len(),
+        duration: Duration::ZERO,
+        truncated: false,
+    };
+
+    queue::play_tracks(ctx, guild_id, tracks, Some(requester), EnqueueAt::Back).await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str, artist: Option<&str>, title: Option<&str>, duration: Option<u64>) -> Entry {
+        Entry {
+            source_url: url.to_string(),
+            title: title.map(str::to_string),
+            artist: artist.map(str::to_string),
+            duration,
+        }
+    }
+
+    fn fields(e: &Entry) -> (&str, Option<&str>, Option<&str>, Option<u64>) {
+        (
+            e.source_url.as_str(),
+            e.artist.as_deref(),
+            e.title.as_deref(),
+            e.duration,
+        )
+    }
+
+    #[test]
+    fn m3u_round_trip() {
+        let entries = vec![
+            entry(
+                "https://example.com/a",
+                Some("Artist"),
+                Some("Song"),
+                Some(212),
+            ),
+            entry("https://example.com/b", None, Some("Untitled"), None),
+            entry("https://example.com/c", None, None, Some(0)),
+        ];
+
+        let m3u = write_m3u(&entries);
+        let read = read_m3u(&m3u);
+
+        assert_eq!(
+            read.iter().map(fields).collect::<Vec<_>>(),
+            entries.iter().map(fields).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn m3u_unknown_duration_is_negative() {
+        let m3u = write_m3u(&[entry("https://example.com/a", None, None, None)]);
+
+        assert!(m3u.contains("#EXTINF:-1,"));
+    }
+
+    #[test]
+    fn m3u_reads_negative_duration_as_unknown() {
+        let read = read_m3u("#EXTM3U\n#EXTINF:-1,Artist - Song\nhttps://example.com/a\n");
+
+        assert_eq!(
+            read.iter().map(fields).collect::<Vec<_>>(),
+            vec![("https://example.com/a", Some("Artist"), Some("Song"), None)]
+        );
+    }
+
+    #[test]
+    fn m3u_reads_plain_lists() {
+        let read = read_m3u("https://example.com/a\n\n# comment\nhttps://example.com/b\n");
+
+        assert_eq!(
+            read.iter()
+                .map(|e| e.source_url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+        assert!(read
+            .iter()
+            .all(|e| e.title.is_none() && e.duration.is_none()));
+    }
+
+    #[test]
+    fn imports_web_local_and_radio_urls() {
+        for url in [
+            "https://example.com/a",
+            "<http://example.com/a>",
+            "local:albums/song.flac",
+            "radio:https://example.com/stream.mp3",
+        ] {
+            assert!(import_url(url).is_some(), "{:?} should be allowed", url);
+        }
+    }
+
+    #[test]
+    fn rejects_other_urls() {
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com/a",
+            "radio:file:///etc/passwd",
+            "radio:",
+            "not a url",
+        ] {
+            assert!(import_url(url).is_none(), "{:?} should be rejected", url);
+        }
+    }
+}
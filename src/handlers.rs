@@ -1,15 +1,40 @@
+use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use serenity::{async_trait, model::prelude::*, prelude::*};
 
-use songbird::{Event, EventContext, EventHandler as VoiceEventHandler};
+use songbird::{tracks::TrackState, Event, EventContext, EventHandler as VoiceEventHandler};
 use tracing::{event, instrument, Level};
 
-use crate::effects::{self, now_playing};
+use crate::effects::{
+    self, autoplay, config, crossfade, history, loudness, now_playing, persist, prefetch,
+    queue::{self, EnqueueAt, LoopMode},
+};
 use crate::emit;
+use crate::slash_commands;
 use crate::structs::EventConfig;
+use crate::utils::SunnyResult;
 
-pub struct Handler;
+/// Runs an effect in the background, most of them take a while or lock the call
+/// and songbird waits for every handler before firing the next event
+fn spawn_effect<F, Fut, T>(cfg: &EventConfig, effect: F)
+where
+    F: FnOnce(Context, GuildId) -> Fut,
+    Fut: Future<Output = SunnyResult<T>> + Send + 'static,
+{
+    let fut = effect(cfg.ctx.clone(), cfg.guild_id);
+    tokio::spawn(async move { emit!(fut.await, Level::WARN) });
+}
+
+/// Tracks which never played were removed rather than finished
+fn was_played(state: &TrackState) -> bool {
+    !state.play_time.is_zero()
+}
+
+pub struct Handler {
+    /// Registers the slash commands for just this guild instead of globally
+    pub slash_guild_id: Option<GuildId>,
+}
 
 #[async_trait]
 impl EventHandler for Handler {
@@ -22,6 +47,20 @@ impl EventHandler for Handler {
         let status = OnlineStatus::DoNotDisturb;
 
         ctx.set_presence(Some(activity), status).await;
+
+        let res = slash_commands::register(&ctx, self.slash_guild_id).await;
+
+        emit!(res, Level::ERROR);
+
+        let res = persist::restore_queues(&ctx).await;
+
+        emit!(res, Level::ERROR);
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::ApplicationCommand(cmd) = interaction {
+            slash_commands::handle(&ctx, &cmd).await;
+        }
     }
 }
 
@@ -35,9 +74,14 @@ impl VoiceEventHandler for TrackPlayNotifier {
     #[instrument(name = "track_play_notifier_handler")]
     async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
         if let EventContext::Track(_track) = event {
-            let res =
-                now_playing::send_embed(&self.cfg.ctx, self.cfg.guild_id, self.cfg.text_channel_id)
-                    .await;
+            let config = config::get_config(self.cfg.guild_id).await;
+
+            if !config.now_playing {
+                return None;
+            }
+
+            let channel_id = config.announce_channel.unwrap_or(self.cfg.text_channel_id);
+            let res = now_playing::send_embed(&self.cfg.ctx, self.cfg.guild_id, channel_id).await;
 
             emit!(res, Level::WARN);
         }
@@ -46,6 +90,202 @@ impl VoiceEventHandler for TrackPlayNotifier {
     }
 }
 
+/// Keeps looping every track that starts while the loop mode is [`LoopMode::Track`]
+#[derive(Debug)]
+pub struct LoopTrackHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for LoopTrackHandler {
+    #[instrument(name = "loop_track_handler")]
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = event {
+            if queue::get_loop_mode(self.cfg.guild_id).await == LoopMode::Track {
+                for (_state, handle) in tracks.iter() {
+                    emit!(handle.enable_loop(), Level::WARN);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Evens out the loudness of the track which started and the one after it,
+/// see [`loudness::normalize_upcoming`]
+#[derive(Debug)]
+pub struct NormalizeHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for NormalizeHandler {
+    #[instrument(name = "normalize_handler")]
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        spawn_effect(&self.cfg, |ctx, guild_id| async move {
+            loudness::normalize_upcoming(&ctx, guild_id).await
+        });
+
+        None
+    }
+}
+
+/// Fades songs which are about to end into the next one and brings the next one
+/// to its full volume once they ended, see [`crossfade::crossfade`]
+#[derive(Debug)]
+pub struct CrossfadeHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for CrossfadeHandler {
+    #[instrument(name = "crossfade_handler")]
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = event {
+            for (state, handle) in tracks.iter() {
+                let ended = state.playing.is_done() && was_played(state);
+
+                // This runs a few times a second, so skip songs which are far from their end
+                if !ended && !crossfade::is_ending(state, handle).await {
+                    continue;
+                }
+
+                let state = **state;
+                let handle = (*handle).clone();
+
+                spawn_effect(&self.cfg, |ctx, guild_id| async move {
+                    if ended {
+                        crossfade::finish_crossfade(&ctx, guild_id, &handle).await
+                    } else {
+                        crossfade::crossfade(&ctx, guild_id, &state, &handle).await
+                    }
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Resolves the next songs of the queue ahead of time, see [`prefetch::prefetch_upcoming`]
+#[derive(Debug)]
+pub struct PrefetchHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for PrefetchHandler {
+    #[instrument(name = "prefetch_handler")]
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        spawn_effect(&self.cfg, |ctx, guild_id| async move {
+            prefetch::prefetch_upcoming(&ctx, guild_id).await
+        });
+
+        None
+    }
+}
+
+/// Re-enqueues finished tracks at the back while the loop mode is [`LoopMode::Queue`]
+#[derive(Debug)]
+pub struct LoopQueueHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for LoopQueueHandler {
+    #[instrument(name = "loop_queue_handler")]
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = event {
+            if queue::get_loop_mode(self.cfg.guild_id).await != LoopMode::Queue {
+                return None;
+            }
+
+            for (state, handle) in tracks.iter() {
+                if !was_played(state) {
+                    continue;
+                }
+
+                let metadata = handle.metadata().clone();
+                let requester = effects::get_requester(handle).await.map(|r| r.user_id);
+
+                spawn_effect(&self.cfg, |ctx, guild_id| async move {
+                    queue::play_tracks(&ctx, guild_id, vec![metadata], requester, EnqueueAt::End)
+                        .await
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Remembers every track which finished playing in the guild's history
+#[derive(Debug)]
+pub struct HistoryRecorder {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for HistoryRecorder {
+    #[instrument(name = "history_recorder")]
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = event {
+            for (state, handle) in tracks.iter() {
+                if was_played(state) {
+                    history::record(self.cfg.guild_id, handle).await;
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Plays a related song once the queue runs out, see [`autoplay::autoplay`]
+#[derive(Debug)]
+pub struct AutoplayHandler {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for AutoplayHandler {
+    #[instrument(name = "autoplay_handler")]
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = event {
+            for (state, handle) in tracks.iter() {
+                let state = **state;
+                let handle = (*handle).clone();
+
+                spawn_effect(&self.cfg, |ctx, guild_id| async move {
+                    autoplay::autoplay(&ctx, guild_id, &state, &handle).await
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Saves the queue whenever it might have changed, see [`persist::save_queue`]
+#[derive(Debug)]
+pub struct QueueSnapshotter {
+    pub cfg: EventConfig,
+}
+
+#[async_trait]
+impl VoiceEventHandler for QueueSnapshotter {
+    #[instrument(name = "queue_snapshotter")]
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        // Saving locks the call, so leave it to the other events first
+        let ctx = self.cfg.ctx.clone();
+        let guild_id = self.cfg.guild_id;
+        tokio::spawn(async move { persist::save_queue(&ctx, guild_id).await });
+
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct TimeoutHandler {
     pub cfg: EventConfig,
@@ -69,7 +309,8 @@ impl VoiceEventHandler for TimeoutHandler {
         ) {
             let prev = self.timer.fetch_add(1, Ordering::Relaxed);
 
-            if prev >= 5 {
+            // Counts the minutes without listeners, minus the current one
+            if prev + 1 >= config::get_config(self.cfg.guild_id).await.idle_timeout as usize {
                 let res = effects::leave(&self.cfg.ctx, self.cfg.guild_id).await;
 
                 emit!(res, Level::WARN);
@@ -6,6 +6,9 @@ mod commands;
 mod effects;
 mod handlers;
 mod hooks;
+mod slash_commands;
+mod sources;
+mod storage;
 mod structs;
 mod utils;
 
@@ -15,11 +18,14 @@ use commands::*;
 use hooks::{after_hook, dispatch_error_hook};
 
 use dotenv::dotenv;
+use effects::config::{self, CMD_PREFIX};
 
 use handlers::Handler;
+use once_cell::sync::Lazy;
 use serenity::{
     client::Client,
     framework::{standard::macros::group, StandardFramework},
+    model::id::GuildId,
 };
 
 use tokio::select;
@@ -30,19 +36,36 @@ use tracing::{event, Level};
 
 #[group]
 #[commands(
+    config,
     join,
     leave,
+    loop_track,
+    loop_queue,
     pause,
     play,
     play_next,
+    radio,
     ping,
     resume,
     remove_at,
+    clear,
+    seek,
+    forward,
+    rewind,
     shuffle,
     skip,
     stop,
     swap,
+    move_track,
+    volume,
+    filter,
     now_playing,
+    history,
+    playlist,
+    export,
+    import,
+    previous,
+    search,
     queue
 )]
 struct General;
@@ -64,11 +87,19 @@ async fn main() {
         .parse()
         .expect("APP_ID needs to be a number");
 
-    let cmd_prefix = env::var("CMD_PREFIX").expect("Environment variable CMD_PREFIX not found");
+    // Fail right away rather than on the first message
+    Lazy::force(&CMD_PREFIX);
+
+    let slash_guild_id = env::var("SLASH_GUILD_ID").ok().map(|id| {
+        GuildId(
+            id.parse()
+                .expect("SLASH_GUILD_ID needs to be a number if present"),
+        )
+    });
 
     let mut sigterm = signal(SignalKind::terminate()).unwrap();
 
-    let mut client = init_bot(token, app_id, cmd_prefix).await;
+    let mut client = init_bot(token, app_id, slash_guild_id).await;
     let shard_manager = client.shard_manager.clone();
 
     select! {
@@ -90,16 +121,22 @@ async fn main() {
     }
 }
 
-pub async fn init_bot(token: String, app_id: u64, cmd_prefix: String) -> Client {
+pub async fn init_bot(token: String, app_id: u64, slash_guild_id: Option<GuildId>) -> Client {
     let framework = StandardFramework::new()
-        .configure(|c| c.prefix(&cmd_prefix))
+        .configure(|c| {
+            c.dynamic_prefix(|_ctx, msg| {
+                Box::pin(async move { Some(config::get_prefix(msg.guild_id).await) })
+            })
+            // Disables the static prefix, the dynamic one falls back to `CMD_PREFIX` already
+            .prefix("")
+        })
         .group(&GENERAL_GROUP)
         .help(&HELP)
         .on_dispatch_error(dispatch_error_hook)
         .after(after_hook);
 
     Client::builder(&token)
-        .event_handler(Handler)
+        .event_handler(Handler { slash_guild_id })
         .framework(framework)
         .register_songbird()
         .application_id(app_id)
@@ -0,0 +1,885 @@
+//! # Slash Commands
+//! The application command front end of Sunny. Every command here calls into
+//! the same [`effects`] as its prefix counterpart in [`crate::commands`].
+
+use std::num::NonZeroUsize;
+
+use serenity::{
+    builder::CreateApplicationCommands,
+    client::Context,
+    http::AttachmentType,
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        interactions::{
+            application_command::{
+                ApplicationCommand, ApplicationCommandInteraction,
+                ApplicationCommandInteractionDataOptionValue as OptionValue,
+                ApplicationCommandOptionType,
+            },
+            InteractionResponseType,
+        },
+        permissions::Permissions,
+    },
+    prelude::Mentionable,
+};
+use tracing::{event, instrument, Level};
+
+use crate::{
+    checks::{can_remove, has_permission, in_same_voice, is_dj, is_dj_or_requester},
+    commands::{
+        config_reply, export_filename, filter_reply, import_reply, loop_reply, parse_song,
+        playlist_reply, playlists_reply, radio_reply, removed_reply, skip_reply, DEFAULT_SEEK_STEP,
+    },
+    effects::{
+        self,
+        config::{self, ConfigKey},
+        display_queue, history, now_playing,
+        queue::{self, EnqueueAt, LoopMode, RemoveFilter, SeekTarget},
+        search,
+        transfer::{self, Format},
+    },
+    emit,
+    sources::{self, Filter},
+    structs::EventConfig,
+    utils::{SunnyError, SunnyResult},
+};
+
+fn create_commands(c: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    c.create_application_command(|c| {
+        c.name("config")
+            .description("Shows or changes the settings of this server")
+            .create_option(|o| {
+                o.name("action")
+                    .description("What to do")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+                    .add_string_choice("get", "get")
+                    .add_string_choice("set", "set")
+                    .add_string_choice("reset", "reset")
+            })
+            .create_option(|o| {
+                o.name("setting")
+                    .description("The setting, leave empty for all of them")
+                    .kind(ApplicationCommandOptionType::String);
+
+                for key in ConfigKey::ALL {
+                    o.add_string_choice(key.name(), key.name());
+                }
+
+                o
+            })
+            .create_option(|o| {
+                o.name("value")
+                    .description("The new value, for set")
+                    .kind(ApplicationCommandOptionType::String)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("join")
+            .description("Adds Sunny to your current voice channel")
+    })
+    .create_application_command(|c| {
+        c.name("leave")
+            .description("Removes Sunny from the current voice channel and clears the queue")
+    })
+    .create_application_command(|c| {
+        c.name("play")
+            .description("Adds a song to the queue")
+            .create_option(|o| {
+                o.name("song")
+                    .description("A url or something to search for")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("play_next")
+            .description("Adds a song to the front of the queue")
+            .create_option(|o| {
+                o.name("song")
+                    .description("A url or something to search for")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| c.name("pause").description("Pauses the current song"))
+    .create_application_command(|c| {
+        c.name("volume")
+            .description("Shows or sets the volume, new songs keep playing at that volume")
+            .create_option(|o| {
+                o.name("percent")
+                    .description("The new volume, from 0 to 200")
+                    .kind(ApplicationCommandOptionType::Integer)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("filter")
+            .description("Shows or applies an audio filter, new songs keep playing with it")
+            .create_option(|o| {
+                o.name("preset")
+                    .description("The filter to apply, off turns it off again")
+                    .kind(ApplicationCommandOptionType::String);
+
+                for filter in Filter::ALL {
+                    o.add_string_choice(filter.name(), filter.name());
+                }
+
+                o
+            })
+    })
+    .create_application_command(|c| {
+        c.name("resume")
+            .description("Resumes the current song if it was paused")
+    })
+    .create_application_command(|c| {
+        c.name("skip")
+            .description("Skips the current song and starts the next song in the queue")
+    })
+    .create_application_command(|c| {
+        c.name("seek")
+            .description("Jumps to a timestamp in the current song")
+            .create_option(|o| {
+                o.name("timestamp")
+                    .description("Like 1:30, 1:02:03, +30s or -1m")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("forward")
+            .description("Skips ahead in the current song")
+            .create_option(|o| {
+                o.name("amount")
+                    .description("How far to skip ahead, like 30s, defaults to 10 seconds")
+                    .kind(ApplicationCommandOptionType::String)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("rewind")
+            .description("Goes back in the current song")
+            .create_option(|o| {
+                o.name("amount")
+                    .description("How far to go back, like 1m, defaults to 10 seconds")
+                    .kind(ApplicationCommandOptionType::String)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("stop")
+            .description("Stops the current song and clears the queue")
+    })
+    .create_application_command(|c| c.name("shuffle").description("Shuffles your queue badly"))
+    .create_application_command(|c| {
+        c.name("swap")
+            .description("Swaps two songs in the queue by their number")
+            .create_option(|o| {
+                o.name("a")
+                    .description("Position of the first song")
+                    .kind(ApplicationCommandOptionType::Integer)
+                    .required(true)
+            })
+            .create_option(|o| {
+                o.name("b")
+                    .description("Position of the second song")
+                    .kind(ApplicationCommandOptionType::Integer)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("move")
+            .description("Moves a song to another position in the queue")
+            .create_option(|o| {
+                o.name("from")
+                    .description("Current position of the song")
+                    .kind(ApplicationCommandOptionType::Integer)
+                    .required(true)
+            })
+            .create_option(|o| {
+                o.name("to")
+                    .description("New position of the song")
+                    .kind(ApplicationCommandOptionType::Integer)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("remove_at")
+            .description("Removes a song from the queue by its position")
+            .create_option(|o| {
+                o.name("position")
+                    .description("Position of the song, defaults to 1")
+                    .kind(ApplicationCommandOptionType::Integer)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("remove")
+            .description("Removes several songs from the queue at once")
+            .create_option(|o| {
+                o.name("songs")
+                    .description("A range like 3-9, duplicates or unavailable")
+                    .kind(ApplicationCommandOptionType::String)
+            })
+            .create_option(|o| {
+                o.name("user")
+                    .description("Removes every song this user requested")
+                    .kind(ApplicationCommandOptionType::User)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("clear")
+            .description("Removes every song from the queue but the current one")
+    })
+    .create_application_command(|c| {
+        c.name("loop")
+            .description("Keeps repeating the current song, run it again to stop looping")
+    })
+    .create_application_command(|c| {
+        c.name("loop_queue")
+            .description("Keeps repeating the whole queue, run it again to stop looping")
+    })
+    .create_application_command(|c| {
+        c.name("now_playing")
+            .description("Shows the currently playing media")
+    })
+    .create_application_command(|c| c.name("queue").description("Shows the current queue"))
+    .create_application_command(|c| {
+        c.name("export")
+            .description("Sends the queue as a file, which import takes again")
+            .create_option(|o| {
+                o.name("format")
+                    .description("Format of the file, m3u by default")
+                    .kind(ApplicationCommandOptionType::String)
+                    .add_string_choice("m3u", "m3u")
+                    .add_string_choice("json", "json")
+            })
+    })
+    .create_application_command(|c| {
+        c.name("import")
+            .description("Adds every song of the last .m3u or .json file you sent in this channel")
+    })
+    .create_application_command(|c| {
+        c.name("history")
+            .description("Shows the songs which played recently")
+    })
+    .create_application_command(|c| {
+        c.name("playlist")
+            .description("Saves the queue under a name to play it again later")
+            .create_option(|o| {
+                o.name("action")
+                    .description("What to do")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+                    .add_string_choice("list", "list")
+                    .add_string_choice("save", "save")
+                    .add_string_choice("load", "load")
+                    .add_string_choice("show", "show")
+                    .add_string_choice("delete", "delete")
+            })
+            .create_option(|o| {
+                o.name("name")
+                    .description("Name of the playlist")
+                    .kind(ApplicationCommandOptionType::String)
+            })
+            .create_option(|o| {
+                o.name("server")
+                    .description("Use the playlists of the whole server instead of your own")
+                    .kind(ApplicationCommandOptionType::Boolean)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("previous")
+            .description("Plays the last played song again, right after the current one")
+    })
+    .create_application_command(|c| {
+        c.name("radio")
+            .description("Plays a live internet radio stream")
+            .create_option(|o| {
+                o.name("url")
+                    .description("Url of the stream")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| {
+        c.name("search")
+            .description("Shows the top YouTube results, pick one to add it to the queue")
+            .create_option(|o| {
+                o.name("terms")
+                    .description("What to search for")
+                    .kind(ApplicationCommandOptionType::String)
+                    .required(true)
+            })
+    })
+    .create_application_command(|c| c.name("ping").description("Pong"))
+}
+
+/// Registers all slash commands, either globally or for a single guild.
+///
+/// Guild commands show up instantly, which is handy during development,
+/// while global commands can take up to an hour to propagate.
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context, guild_id: Option<GuildId>) -> SunnyResult<()> {
+    let res = match guild_id {
+        Some(guild_id) => guild_id
+            .set_application_commands(&ctx.http, create_commands)
+            .await
+            .map(|_| ()),
+        None => ApplicationCommand::set_global_application_commands(&ctx.http, create_commands)
+            .await
+            .map(|_| ()),
+    };
+
+    res.map_err(|e| SunnyError::log(format!("Failed to register slash commands: {:?}", e).as_str()))
+}
+
+/// Defers the response, runs the invoked command and reports any errors back
+/// to the user.
+#[instrument(skip(ctx, cmd), fields(cmd = %cmd.data.name))]
+pub async fn handle(ctx: &Context, cmd: &ApplicationCommandInteraction) {
+    // Some commands, like play, take longer than the 3 seconds discord allows
+    // for an initial response.
+    let res = cmd
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    if let Err(e) = res {
+        event!(Level::WARN, %e, "Failed to defer interaction");
+        return;
+    }
+
+    let res = match cmd.data.name.as_str() {
+        "config" => config(ctx, cmd).await,
+        "join" => join(ctx, cmd).await,
+        "leave" => leave(ctx, cmd).await,
+        "play" => play(ctx, cmd, EnqueueAt::Back).await,
+        "play_next" => play(ctx, cmd, EnqueueAt::Front).await,
+        "pause" => pause(ctx, cmd).await,
+        "volume" => volume(ctx, cmd).await,
+        "filter" => filter(ctx, cmd).await,
+        "resume" => resume(ctx, cmd).await,
+        "skip" => skip(ctx, cmd).await,
+        "seek" => seek(ctx, cmd).await,
+        "forward" => seek(ctx, cmd).await,
+        "rewind" => seek(ctx, cmd).await,
+        "stop" => stop(ctx, cmd).await,
+        "shuffle" => shuffle(ctx, cmd).await,
+        "swap" => swap(ctx, cmd).await,
+        "move" => move_track(ctx, cmd).await,
+        "remove_at" => remove_at(ctx, cmd).await,
+        "remove" => remove(ctx, cmd).await,
+        "clear" => clear(ctx, cmd).await,
+        "loop" => toggle_loop(ctx, cmd, LoopMode::Track).await,
+        "loop_queue" => toggle_loop(ctx, cmd, LoopMode::Queue).await,
+        "now_playing" => now_playing(ctx, cmd).await,
+        "queue" => queue(ctx, cmd).await,
+        "history" => history(ctx, cmd).await,
+        "playlist" => playlist(ctx, cmd).await,
+        "export" => export(ctx, cmd).await,
+        "import" => import(ctx, cmd).await,
+        "previous" => previous(ctx, cmd).await,
+        "search" => search(ctx, cmd).await,
+        "radio" => radio(ctx, cmd).await,
+        "ping" => respond(ctx, cmd, "Pong!").await,
+        _ => Err(SunnyError::log("Unknown slash command")),
+    };
+
+    if let Err(error) = res {
+        match &error {
+            SunnyError::User(user) => emit!(respond(ctx, cmd, user).await, Level::WARN),
+            SunnyError::Log(log) => {
+                emit!(respond(ctx, cmd, "Something went wrong").await, Level::WARN);
+                event!(Level::WARN, ?log);
+            }
+            SunnyError::UserAndLog { user, log } => {
+                emit!(respond(ctx, cmd, user).await, Level::WARN);
+                event!(Level::WARN, ?log);
+            }
+        }
+    }
+}
+
+/// Replaces the deferred response with `content`
+async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    content: impl ToString,
+) -> SunnyResult<()> {
+    cmd.edit_original_interaction_response(&ctx.http, |r| r.content(content))
+        .await
+        .map_err(|e| SunnyError::log(format!("Unable to edit response: {:?}", e).as_str()))?;
+
+    Ok(())
+}
+
+fn get_guild_id(cmd: &ApplicationCommandInteraction) -> SunnyResult<GuildId> {
+    cmd.guild_id
+        .ok_or_else(|| SunnyError::user("This command only works in servers"))
+}
+
+fn get_option<'a>(cmd: &'a ApplicationCommandInteraction, name: &str) -> Option<&'a OptionValue> {
+    cmd.data
+        .options
+        .iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.resolved.as_ref())
+}
+
+fn get_string<'a>(cmd: &'a ApplicationCommandInteraction, name: &str) -> Option<&'a str> {
+    match get_option(cmd, name) {
+        Some(OptionValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+fn get_user(cmd: &ApplicationCommandInteraction, name: &str) -> Option<UserId> {
+    match get_option(cmd, name) {
+        Some(OptionValue::User(user, _)) => Some(user.id),
+        _ => None,
+    }
+}
+
+fn get_bool(cmd: &ApplicationCommandInteraction, name: &str) -> Option<bool> {
+    match get_option(cmd, name) {
+        Some(OptionValue::Boolean(b)) => Some(*b),
+        _ => None,
+    }
+}
+
+fn get_integer(cmd: &ApplicationCommandInteraction, name: &str) -> Option<i64> {
+    match get_option(cmd, name) {
+        Some(OptionValue::Integer(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+/// Gets a queue position option, which has to be a positive integer
+fn get_position(cmd: &ApplicationCommandInteraction, name: &str) -> SunnyResult<Option<usize>> {
+    get_integer(cmd, name)
+        .map(|i| usize::try_from(i).map_err(|_| SunnyError::user("Invalid arguments")))
+        .transpose()
+}
+
+async fn config(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    has_permission(ctx, guild_id, cmd.user.id, Permissions::MANAGE_GUILD).await?;
+
+    let reply = config_reply(
+        guild_id,
+        get_string(cmd, "action").unwrap_or("get"),
+        get_string(cmd, "setting"),
+        get_string(cmd, "value").unwrap_or_default(),
+    )
+    .await?;
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn join(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild = ctx
+        .cache
+        .guild(get_guild_id(cmd)?)
+        .await
+        .ok_or_else(|| SunnyError::log("interaction guild could not be found"))?;
+
+    // The user's voice channel id
+    let voice_channel_id: ChannelId = guild
+        .voice_states
+        .get(&cmd.user.id)
+        .and_then(|vs| vs.channel_id)
+        .ok_or_else(|| SunnyError::user("Not in a voice"))?;
+
+    let bot_id = ctx.cache.current_user_id().await;
+    let same_voice =
+        guild.voice_states.get(&bot_id).and_then(|vs| vs.channel_id) == Some(voice_channel_id);
+
+    if same_voice {
+        return Err(SunnyError::user("Already in that voice channel!"));
+    }
+
+    let call_m = effects::join(&EventConfig {
+        ctx: ctx.clone(),
+        guild_id: guild.id,
+        text_channel_id: cmd.channel_id,
+        voice_channel_id,
+    })
+    .await?;
+
+    effects::deafen(call_m).await;
+
+    respond(ctx, cmd, format!("Joined {}", voice_channel_id.mention())).await
+}
+
+async fn leave(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+    is_dj(ctx, guild_id, cmd.user.id).await?;
+
+    effects::leave(ctx, guild_id).await?;
+
+    respond(ctx, cmd, "Left voice").await
+}
+
+async fn play(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    enqueue_at: EnqueueAt,
+) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let url = parse_song(get_string(cmd, "song").unwrap_or_default())?;
+
+    if sources::is_playlist(&url) {
+        let summary =
+            queue::play_playlist(ctx, guild_id, url, Some(cmd.user.id), enqueue_at).await?;
+        return respond(ctx, cmd, playlist_reply(&summary)).await;
+    }
+
+    let reply = match enqueue_at {
+        EnqueueAt::Front => {
+            queue::play(ctx, guild_id, url, Some(cmd.user.id), EnqueueAt::Front).await?;
+            "Added song to front of queue".to_string()
+        }
+        EnqueueAt::Back | EnqueueAt::End => {
+            match queue::play(ctx, guild_id, url, Some(cmd.user.id), EnqueueAt::Back).await? {
+                0 => "Started playing the song".to_string(),
+                position => format!("Added song to queue: position {}", position),
+            }
+        }
+    };
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn volume(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let volume = match get_integer(cmd, "percent") {
+        Some(volume) => u16::try_from(volume)
+            .map_err(|_| SunnyError::user("The volume has to be a number from 0 to 200"))?,
+        None => {
+            let volume = config::get_config(guild_id).await.volume;
+            return respond(ctx, cmd, format!("The volume is {}%", volume)).await;
+        }
+    };
+
+    queue::set_volume(ctx, guild_id, volume).await?;
+
+    respond(ctx, cmd, format!("Set the volume to {}%", volume)).await
+}
+
+async fn filter(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let reply = filter_reply(ctx, guild_id, get_string(cmd, "preset")).await?;
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn pause(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    queue::pause(ctx, guild_id).await?;
+
+    respond(ctx, cmd, "Track paused").await
+}
+
+async fn resume(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    queue::resume(ctx, guild_id).await?;
+
+    respond(ctx, cmd, "Track resumed").await
+}
+
+async fn skip(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let vote = queue::vote_skip(ctx, guild_id, cmd.user.id).await?;
+
+    respond(ctx, cmd, skip_reply(&vote)).await
+}
+
+async fn seek(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let amount = get_string(cmd, "amount")
+        .map(queue::parse_duration)
+        .transpose()?
+        .unwrap_or(DEFAULT_SEEK_STEP);
+
+    let target = match cmd.data.name.as_str() {
+        "forward" => SeekTarget::Forward(amount),
+        "rewind" => SeekTarget::Backward(amount),
+        _ => get_string(cmd, "timestamp")
+            .ok_or_else(|| SunnyError::user("Invalid arguments"))?
+            .parse()?,
+    };
+
+    let position = queue::seek(ctx, guild_id, target).await?;
+
+    respond(
+        ctx,
+        cmd,
+        format!("Seeked to {}", effects::format_duration(position)),
+    )
+    .await
+}
+
+async fn stop(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+    is_dj(ctx, guild_id, cmd.user.id).await?;
+
+    queue::stop(ctx, guild_id).await?;
+
+    respond(ctx, cmd, "Queue cleared.").await
+}
+
+async fn shuffle(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+    is_dj(ctx, guild_id, cmd.user.id).await?;
+
+    queue::shuffle(ctx, guild_id).await?;
+
+    respond(ctx, cmd, "Queue Shuffled :game_die:!").await
+}
+
+async fn swap(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let a = get_position(cmd, "a")?.ok_or_else(|| SunnyError::user("Invalid arguments"))?;
+    let b = get_position(cmd, "b")?.ok_or_else(|| SunnyError::user("Invalid arguments"))?;
+
+    is_dj_or_requester(ctx, guild_id, cmd.user.id, a..=a).await?;
+    is_dj_or_requester(ctx, guild_id, cmd.user.id, b..=b).await?;
+
+    let (t1, t2) = queue::swap(ctx, guild_id, a, b).await?;
+
+    respond(
+        ctx,
+        cmd,
+        format!(
+            "Swapped `{}` and `{}`",
+            effects::get_song(t1.metadata()),
+            effects::get_song(t2.metadata())
+        ),
+    )
+    .await
+}
+
+async fn move_track(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let from = get_position(cmd, "from")?.ok_or_else(|| SunnyError::user("Invalid arguments"))?;
+    let to = get_position(cmd, "to")?.ok_or_else(|| SunnyError::user("Invalid arguments"))?;
+
+    is_dj_or_requester(ctx, guild_id, cmd.user.id, from..=from).await?;
+
+    let track = queue::move_track(ctx, guild_id, from, to).await?;
+
+    respond(
+        ctx,
+        cmd,
+        format!(
+            "Moved `{}` to position {}",
+            effects::get_song(track.metadata()),
+            to
+        ),
+    )
+    .await
+}
+
+async fn remove_at(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let index = match get_position(cmd, "position")? {
+        Some(i) => NonZeroUsize::new(i).ok_or_else(|| {
+            SunnyError::user("A song index of 0 is invalid (The queue starts at 1)")
+        })?,
+        #[allow(clippy::unwrap_used)]
+        None => NonZeroUsize::new(1).unwrap(),
+    };
+
+    is_dj_or_requester(ctx, guild_id, cmd.user.id, index.get()..=index.get()).await?;
+
+    let q = queue::remove_at(ctx, guild_id, index).await?;
+
+    respond(
+        ctx,
+        cmd,
+        format!("Removed: `{}`", effects::get_song(q.metadata())),
+    )
+    .await
+}
+
+async fn remove(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let filter = match (get_user(cmd, "user"), get_string(cmd, "songs")) {
+        (Some(user_id), _) => RemoveFilter::User(user_id),
+        (None, Some(songs)) => songs.parse()?,
+        (None, None) => return Err(SunnyError::user("Tell me which songs to remove")),
+    };
+
+    can_remove(ctx, guild_id, cmd.user.id, filter).await?;
+
+    let removed = queue::remove(ctx, guild_id, filter).await?;
+
+    respond(ctx, cmd, removed_reply(&removed)).await
+}
+
+async fn clear(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+    is_dj(ctx, guild_id, cmd.user.id).await?;
+
+    let removed = queue::remove(ctx, guild_id, RemoveFilter::All).await?;
+
+    respond(ctx, cmd, removed_reply(&removed)).await
+}
+
+async fn toggle_loop(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    mode: LoopMode,
+) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let mode = queue::toggle_loop(ctx, guild_id, mode).await?;
+
+    respond(ctx, cmd, loop_reply(mode)).await
+}
+
+async fn now_playing(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+
+    respond(ctx, cmd, "Now playing:").await?;
+
+    now_playing::send_embed(ctx, guild_id, cmd.channel_id).await
+}
+
+async fn queue(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+
+    respond(ctx, cmd, "Current queue:").await?;
+
+    display_queue::send_embed(ctx, guild_id, cmd.channel_id).await
+}
+
+async fn playlist(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+
+    let reply = playlists_reply(
+        ctx,
+        guild_id,
+        cmd.user.id,
+        get_string(cmd, "action").unwrap_or("list"),
+        get_bool(cmd, "server").unwrap_or_default(),
+        get_string(cmd, "name"),
+    )
+    .await?;
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn export(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+
+    let format = get_string(cmd, "format").unwrap_or("m3u").parse()?;
+    let (file, count) = transfer::export(ctx, guild_id, format).await?;
+
+    respond(ctx, cmd, format!("Exported {} songs", count)).await?;
+
+    cmd.create_followup_message(&ctx.http, |f| {
+        f.add_file(AttachmentType::Bytes {
+            data: file.into(),
+            filename: export_filename(format),
+        })
+    })
+    .await
+    .map_err(|e| SunnyError::log(format!("Unable to send export: {:?}", e).as_str()))?;
+
+    Ok(())
+}
+
+/// Messages searched for the file to import
+const IMPORT_SEARCH_LIMIT: u64 = 50;
+
+async fn import(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    // Slash commands can't take files, so look for one the user sent before
+    let messages = cmd
+        .channel_id
+        .messages(&ctx.http, |r| r.limit(IMPORT_SEARCH_LIMIT))
+        .await
+        .map_err(|e| SunnyError::log(format!("Unable to get messages: {:?}", e).as_str()))?;
+
+    let attachment = messages
+        .iter()
+        .filter(|m| m.author.id == cmd.user.id)
+        .flat_map(|m| &m.attachments)
+        .find(|a| Format::from_filename(&a.filename).is_ok())
+        .ok_or_else(|| SunnyError::user("Send an `.m3u` or `.json` file in this channel first"))?;
+
+    let reply = import_reply(ctx, guild_id, cmd.user.id, attachment).await?;
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn history(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+
+    respond(ctx, cmd, "Recently played:").await?;
+
+    history::send_embed(ctx, guild_id, cmd.channel_id).await
+}
+
+async fn previous(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let m = history::play_previous(ctx, guild_id).await?;
+
+    respond(
+        ctx,
+        cmd,
+        format!("Added {} to the front of the queue", effects::get_song(&m)),
+    )
+    .await
+}
+
+async fn radio(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let url = get_string(cmd, "url").unwrap_or_default();
+    let reply = radio_reply(ctx, guild_id, cmd.user.id, url).await?;
+
+    respond(ctx, cmd, reply).await
+}
+
+async fn search(ctx: &Context, cmd: &ApplicationCommandInteraction) -> SunnyResult<()> {
+    let guild_id = get_guild_id(cmd)?;
+    in_same_voice(ctx, guild_id, cmd.user.id).await?;
+
+    let terms = get_string(cmd, "terms").unwrap_or_default();
+
+    respond(ctx, cmd, format!("Searching for \"{}\"", terms)).await?;
+
+    search::send_embed(ctx, guild_id, cmd.channel_id, cmd.user.id, terms).await
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use serenity::{async_trait, model::id::GuildId};
+use songbird::input::{
+    error::{Error, Result},
+    restartable::Restart,
+    Codec, Container, Input, Metadata, Restartable,
+};
+use tokio::{fs, process::Command as TokioCommand};
+use tracing::instrument;
+use url::Url;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::filter::{get_filter, Timeline};
+
+/// Marks urls of files in [`MEDIA_DIR`], e.g. `local:albums/song.flac`
+pub const LOCAL_PREFIX: &str = "local:";
+
+/// Directory `local:` files are played from, configured through the
+/// `MEDIA_DIR` environment variable. Without it local files can't be played.
+static MEDIA_DIR: Lazy<Option<PathBuf>> =
+    Lazy::new(|| env::var("MEDIA_DIR").ok().map(PathBuf::from));
+
+/// Streams a file straight through ffmpeg, either from disk or over http
+struct FileRestarter {
+    /// Path or url of the file
+    uri: String,
+    /// What the file is played again with, e.g. after a restart
+    source_url: String,
+    /// Whose filter is applied
+    guild_id: GuildId,
+    /// Where in the file the track is
+    timeline: Timeline,
+    metadata: Option<Metadata>,
+}
+
+#[async_trait]
+impl Restart for FileRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> Result<Input> {
+        let filter = get_filter(self.guild_id).await;
+
+        let seek_args = time
+            .map(|t| self.timeline.restart(t, filter))
+            .map(|t| vec!["-ss".to_string(), format!("{:.3}", t.as_secs_f64())])
+            .unwrap_or_default();
+
+        let filter_args = filter.args();
+
+        let ffmpeg = Command::new("ffmpeg")
+            .args(&seek_args)
+            .args(["-i", &self.uri])
+            .args(&filter_args)
+            .args([
+                "-f",
+                "s16le",
+                "-ac",
+                "2",
+                "-ar",
+                "48000",
+                "-acodec",
+                "pcm_f32le",
+                "-",
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        Ok(Input::new(
+            true,
+            ffmpeg.into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            self.metadata.clone(),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)> {
+        if self.metadata.is_none() {
+            self.metadata = Some(Metadata {
+                source_url: Some(self.source_url.clone()),
+                ..probe(&self.uri).await?
+            });
+        }
+
+        Ok((self.metadata.clone(), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// Collects the tags of the format and the audio stream, which is where
+/// ogg and opus files keep theirs. Tag names are case insensitive.
+fn collect_tags(format: Option<&Value>, stream: Option<&Value>) -> HashMap<String, String> {
+    [format, stream]
+        .iter()
+        .flatten()
+        .filter_map(|v| v.get("tags").and_then(Value::as_object))
+        .flatten()
+        .filter_map(|(k, v)| Some((k.to_lowercase(), v.as_str()?.to_string())))
+        .collect()
+}
+
+/// The file name without its extension, for files without a title tag
+fn file_stem(uri: &str) -> Option<String> {
+    let path = match Url::parse(uri) {
+        Ok(url) if url.scheme() != "file" => url.path_segments()?.next_back()?.to_string(),
+        _ => uri.to_string(),
+    };
+
+    Path::new(&path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.replace('_', " "))
+}
+
+/// Reads the tags of the file at `uri` with ffprobe
+async fn probe(uri: &str) -> Result<Metadata> {
+    let output = TokioCommand::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-of",
+            "json",
+            "-show_format",
+            "-show_streams",
+            "-i",
+            uri,
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    // Files ffprobe can't read can't be played either
+    if !output.status.success() {
+        return Err(Error::Metadata);
+    }
+
+    let value: Value = serde_json::from_slice(&output.stdout).map_err(|error| Error::Json {
+        error,
+        parsed_text: String::from_utf8_lossy(&output.stdout).to_string(),
+    })?;
+
+    let format = value.get("format");
+    let stream = value
+        .get("streams")
+        .and_then(Value::as_array)
+        .and_then(|s| {
+            s.iter()
+                .find(|s| s.get("codec_type").and_then(Value::as_str) == Some("audio"))
+        });
+
+    let mut tags = collect_tags(format, stream);
+
+    Ok(Metadata {
+        title: tags.remove("title").or_else(|| file_stem(uri)),
+        artist: tags
+            .remove("artist")
+            .or_else(|| tags.remove("album_artist")),
+        date: tags.remove("date"),
+        duration: format
+            .and_then(|f| f.get("duration"))
+            .and_then(Value::as_str)
+            .and_then(|d| d.parse().ok())
+            .map(Duration::from_secs_f64),
+        channels: stream
+            .and_then(|s| s.get("channels"))
+            .and_then(Value::as_u64)
+            .and_then(|c| u8::try_from(c).ok()),
+        sample_rate: stream
+            .and_then(|s| s.get("sample_rate"))
+            .and_then(Value::as_str)
+            .and_then(|r| r.parse().ok()),
+        ..Metadata::default()
+    })
+}
+
+/// Resolves `path` in [`MEDIA_DIR`], making sure it doesn't lead out of it
+pub(super) async fn local_path(path: &str) -> SunnyResult<PathBuf> {
+    let media_dir = MEDIA_DIR
+        .as_ref()
+        .ok_or_else(|| SunnyError::user("Playing local files isn't set up"))?;
+
+    let not_found = || SunnyError::user("Couldn't find that file");
+
+    let media_dir = fs::canonicalize(media_dir).await.map_err(|e| {
+        SunnyError::user_and_log(
+            "Playing local files isn't set up",
+            format!("Failed to open MEDIA_DIR: {}", e).as_str(),
+        )
+    })?;
+
+    let full_path = fs::canonicalize(media_dir.join(path.trim_start_matches('/')))
+        .await
+        .map_err(|_| not_found())?;
+
+    if !full_path.starts_with(&media_dir) || !full_path.is_file() {
+        return Err(not_found());
+    }
+
+    Ok(full_path)
+}
+
+/// Creates a lazy source for a file at `uri`, which is either an http url or
+/// a path in [`MEDIA_DIR`] prefixed with [`LOCAL_PREFIX`].
+///
+/// Without `metadata` the file is probed for its tags up front.
+#[instrument]
+pub async fn file(
+    uri: String,
+    metadata: Option<Metadata>,
+    guild_id: GuildId,
+    timeline: Timeline,
+) -> SunnyResult<Restartable> {
+    let path = match uri.strip_prefix(LOCAL_PREFIX) {
+        Some(path) => local_path(path).await?.to_string_lossy().to_string(),
+        None => uri.clone(),
+    };
+
+    let restarter = FileRestarter {
+        uri: path,
+        source_url: uri,
+        guild_id,
+        timeline,
+        metadata,
+    };
+
+    Restartable::new(restarter, true).await.map_err(|e| {
+        SunnyError::user_and_log(
+            "Couldn't play that file",
+            format!("Error sourcing ffmpeg {:?}", e).as_str(),
+        )
+    })
+}
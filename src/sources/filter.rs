@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serenity::{model::id::GuildId, prelude::Mutex};
+
+use crate::utils::SunnyError;
+
+/// Audio filter presets, applied by ffmpeg to everything a guild plays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    Off,
+    BassBoost,
+    /// Faster and higher pitched
+    Nightcore,
+    /// Slower and lower pitched
+    Vaporwave,
+    /// Pans the sound around the listener's head
+    EightD,
+    /// EBU R128 loudness normalization, evens out quiet and loud songs
+    Normalize,
+}
+
+impl Filter {
+    pub const ALL: [Self; 6] = [
+        Self::Off,
+        Self::BassBoost,
+        Self::Nightcore,
+        Self::Vaporwave,
+        Self::EightD,
+        Self::Normalize,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::BassBoost => "bassboost",
+            Self::Nightcore => "nightcore",
+            Self::Vaporwave => "vaporwave",
+            Self::EightD => "8d",
+            Self::Normalize => "normalize",
+        }
+    }
+
+    /// The ffmpeg `-af` filter chain of the preset.
+    ///
+    /// Speed changes resample to a different rate first, so the pitch moves along
+    /// with the tempo like on a sped up record.
+    const fn chain(self) -> Option<&'static str> {
+        match self {
+            Self::Off => None,
+            Self::BassBoost => Some("bass=g=10:f=110:w=0.6,alimiter=limit=0.9"),
+            Self::Nightcore => Some("aresample=48000,asetrate=60000,aresample=48000"),
+            Self::Vaporwave => Some("aresample=48000,asetrate=38400,aresample=48000"),
+            Self::EightD => Some("apulsator=hz=0.125:amount=0.8"),
+            Self::Normalize => Some("loudnorm=I=-16:TP=-1.5:LRA=11"),
+        }
+    }
+
+    /// How much faster than the song the preset plays, matching the rates of [`Self::chain`]
+    pub fn speed(self) -> f64 {
+        match self {
+            Self::Nightcore => 1.25,
+            Self::Vaporwave => 0.8,
+            _ => 1.0,
+        }
+    }
+
+    /// The ffmpeg `-af` arguments of the preset
+    pub(super) fn args(self) -> Vec<String> {
+        self.chain()
+            .map(|chain| vec!["-af".to_string(), chain.to_string()])
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Filter {
+    type Err = SunnyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase().replace(['-', '_', ' '], "");
+
+        match s.as_str() {
+            "off" | "none" | "clear" | "reset" => Ok(Self::Off),
+            "bassboost" | "bass" => Ok(Self::BassBoost),
+            "nightcore" | "nc" => Ok(Self::Nightcore),
+            "vaporwave" | "vw" => Ok(Self::Vaporwave),
+            "8d" | "eightd" => Ok(Self::EightD),
+            "normalize" | "normalise" | "loudnorm" => Ok(Self::Normalize),
+            _ => Err(SunnyError::user(
+                format!(
+                    "Unknown filter, try one of {}",
+                    Self::ALL
+                        .iter()
+                        .map(|f| format!("`{}`", f))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .as_str(),
+            )),
+        }
+    }
+}
+
+/// The filter of every guild which turned one on.
+///
+/// Sources look it up every time they (re)start ffmpeg, so a new filter applies
+/// to any track which is restarted or started later on.
+static FILTERS: Lazy<Mutex<HashMap<GuildId, Filter>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub async fn get_filter(guild_id: GuildId) -> Filter {
+    FILTERS
+        .lock()
+        .await
+        .get(&guild_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+pub async fn set_filter(guild_id: GuildId, filter: Filter) {
+    let mut filters = FILTERS.lock().await;
+
+    if filter == Filter::Off {
+        filters.remove(&guild_id);
+    } else {
+        filters.insert(guild_id, filter);
+    }
+}
+
+/// A point of a track at which it played at `speed` from then on
+#[derive(Clone, Copy, Debug)]
+struct Anchor {
+    /// Position of the track, i.e. how long it played
+    position: Duration,
+    /// Where in the song the track was at `position`
+    song: Duration,
+    speed: f64,
+}
+
+impl Default for Anchor {
+    fn default() -> Self {
+        Self {
+            position: Duration::ZERO,
+            song: Duration::ZERO,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Maps the position of a track to where it is in its song.
+///
+/// A track's position counts how long it played, which drifts away from the song
+/// under speed presets. The source restarts at the right spot of the song with it,
+/// while effects seek and check how long a track still plays through it.
+#[derive(Clone, Debug, Default)]
+pub struct Timeline(Arc<StdMutex<Anchor>>);
+
+impl Timeline {
+    fn anchor(&self) -> Anchor {
+        self.0.lock().map(|a| *a).unwrap_or_default()
+    }
+
+    /// Where in the song the track is at `position`
+    pub fn song_position(&self, position: Duration) -> Duration {
+        let anchor = self.anchor();
+
+        if position >= anchor.position {
+            anchor.song + (position - anchor.position).mul_f64(anchor.speed)
+        } else {
+            anchor
+                .song
+                .saturating_sub((anchor.position - position).mul_f64(anchor.speed))
+        }
+    }
+
+    /// The position the track is at `song` of its song, the inverse of [`Self::song_position`]
+    pub fn position(&self, song: Duration) -> Duration {
+        let anchor = self.anchor();
+
+        if song >= anchor.song {
+            anchor.position + (song - anchor.song).div_f64(anchor.speed)
+        } else {
+            anchor
+                .position
+                .saturating_sub((anchor.song - song).div_f64(anchor.speed))
+        }
+    }
+
+    /// Where in the song a source restarting at `position` with `filter` starts.
+    /// The track plays at the speed of `filter` from then on.
+    pub(super) fn restart(&self, position: Duration, filter: Filter) -> Duration {
+        let song = self.song_position(position);
+
+        if let Ok(mut anchor) = self.0.lock() {
+            if (anchor.speed - filter.speed()).abs() > f64::EPSILON {
+                *anchor = Anchor {
+                    position,
+                    song,
+                    speed: filter.speed(),
+                };
+            }
+        }
+
+        song
+    }
+}
@@ -0,0 +1,73 @@
+use std::process::Stdio;
+
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::instrument;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::{file::local_path, is_file, ytdl::stream_url, LOCAL_PREFIX, RADIO_PREFIX};
+
+/// How much of a song is analyzed, in seconds. The start of a song says enough
+/// about its loudness and hour long mixes would take ages otherwise.
+const ANALYZED_SECONDS: &str = "300";
+
+/// Picks what ffmpeg reads the audio of `url` from
+async fn input(url: &str) -> SunnyResult<String> {
+    if url.starts_with(RADIO_PREFIX) {
+        return Err(SunnyError::user("Live streams can't be measured"));
+    }
+
+    if let Some(path) = url.strip_prefix(LOCAL_PREFIX) {
+        return Ok(local_path(path).await?.to_string_lossy().to_string());
+    }
+
+    if is_file(url) {
+        return Ok(url.to_string());
+    }
+
+    stream_url(url).await
+}
+
+/// Gets the report `loudnorm` prints as its last bit of output
+fn parse_report(stderr: &str) -> Option<f64> {
+    let start = stderr.rfind('{')?;
+    let end = stderr.rfind('}')?;
+
+    let report: Value = serde_json::from_str(stderr.get(start..=end)?).ok()?;
+
+    // Silence measures as `-inf`, which doesn't parse
+    report
+        .get("input_i")?
+        .as_str()?
+        .parse()
+        .ok()
+        .filter(|i: &f64| i.is_finite())
+}
+
+/// Measures the integrated loudness of the song behind `url` in LUFS, with an
+/// ffmpeg `loudnorm` analysis pass
+#[instrument]
+pub async fn loudness(url: &str) -> SunnyResult<f64> {
+    let input = input(url).await?;
+
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-vn"])
+        .args(["-t", ANALYZED_SECONDS, "-i", &input])
+        .args(["-af", "loudnorm=print_format=json", "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to run ffmpeg: {}", e).as_str()))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if !output.status.success() {
+        return Err(SunnyError::log(
+            format!("Failed to measure loudness: {}", stderr).as_str(),
+        ));
+    }
+
+    parse_report(&stderr).ok_or_else(|| SunnyError::log("ffmpeg didn't report a loudness"))
+}
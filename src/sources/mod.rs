@@ -0,0 +1,88 @@
+//! # Sources
+//! Sources create the inputs Sunny streams from, and know how to look up
+//! what's behind a url.
+
+mod file;
+mod filter;
+mod loudness;
+mod playlist;
+mod radio;
+mod related;
+mod search;
+mod ytdl;
+
+pub use file::{file, LOCAL_PREFIX};
+pub use filter::{get_filter, set_filter, Filter, Timeline};
+pub use loudness::loudness;
+pub use playlist::{is_playlist, list_playlist, MAX_PLAYLIST_ENTRIES};
+pub use radio::{stream_info, StreamInfo, RADIO_PREFIX};
+pub use related::related;
+pub use search::{first_result, search};
+pub use ytdl::ytdl;
+
+use serenity::model::id::GuildId;
+use songbird::input::{Metadata, Restartable};
+use url::Url;
+
+use crate::utils::SunnyResult;
+
+const YOUTUBE_DL: &str = "youtube-dl";
+
+/// Hosts of files posted in Discord
+const DISCORD_CDN_HOSTS: [&str; 2] = ["cdn.discordapp.com", "media.discordapp.net"];
+
+/// Whether `url` points to a plain file, which doesn't need youtube-dl
+pub fn is_file(url: &str) -> bool {
+    url.starts_with(LOCAL_PREFIX)
+        || matches!(
+            Url::parse(url).ok().as_ref().and_then(Url::host_str),
+            Some(host) if DISCORD_CDN_HOSTS.contains(&host)
+        )
+}
+
+/// Creates a lazy source for `url`, streaming radio stations and files
+/// directly and anything else through youtube-dl. The source plays with
+/// whatever filter `guild_id` has on when it starts.
+///
+/// The [`Timeline`] tells where in the song the track is, which differs from
+/// its position once a speed preset is on.
+pub async fn source(
+    url: String,
+    metadata: Option<Metadata>,
+    guild_id: GuildId,
+) -> SunnyResult<(Restartable, Timeline)> {
+    let timeline = Timeline::default();
+
+    let source = if url.starts_with(RADIO_PREFIX) {
+        radio::radio(url, guild_id).await
+    } else if is_file(&url) {
+        file(url, metadata, guild_id, timeline.clone()).await
+    } else {
+        ytdl(url, metadata, guild_id, timeline.clone()).await
+    }?;
+
+    Ok((source, timeline))
+}
+
+/// Resolves the stream of `url` ahead of time, so it starts without delay.
+/// Radio stations and files are streamed directly, so they don't need it.
+pub async fn prefetch(url: &str) -> SunnyResult<()> {
+    if url.starts_with(RADIO_PREFIX) || is_file(url) {
+        return Ok(());
+    }
+
+    ytdl::prefetch(url).await
+}
+
+/// Strips the embed suppressing `<>` from `url` and makes sure it is valid
+pub fn parse_url(url: &str) -> Option<String> {
+    let url = if url.starts_with('<') && url.ends_with('>') {
+        &url[1..url.len() - 1]
+    } else {
+        url
+    };
+
+    Url::parse(url).ok()?;
+
+    Some(url.to_string())
+}
@@ -0,0 +1,131 @@
+use std::{env, process::Stdio, time::Duration};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use songbird::input::Metadata;
+use tokio::process::Command;
+use tracing::instrument;
+use url::Url;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::YOUTUBE_DL;
+
+/// The most entries a single playlist can add to the queue, configurable
+/// through the `MAX_PLAYLIST_ENTRIES` environment variable.
+pub static MAX_PLAYLIST_ENTRIES: Lazy<usize> = Lazy::new(|| {
+    env::var("MAX_PLAYLIST_ENTRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(50)
+});
+
+#[derive(Debug)]
+pub struct Playlist {
+    pub entries: Vec<Metadata>,
+    /// Whether the playlist had more entries than were listed
+    pub truncated: bool,
+}
+
+/// Checks whether `url` points to a YouTube or SoundCloud playlist.
+///
+/// Videos which are opened from a playlist (`watch?v=...&list=...`) are
+/// treated as just the video.
+pub fn is_playlist(url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    match url.host_str() {
+        Some("youtube.com" | "www.youtube.com" | "m.youtube.com" | "music.youtube.com") => {
+            url.path() == "/playlist" && url.query_pairs().any(|(k, _)| k == "list")
+        }
+        // soundcloud.com/<user>/sets/<playlist>
+        Some("soundcloud.com" | "www.soundcloud.com" | "m.soundcloud.com") => {
+            url.path_segments().and_then(|mut s| s.nth(1)) == Some("sets")
+        }
+        _ => false,
+    }
+}
+
+/// Converts an entry of youtube-dl's flat playlist output into [`Metadata`]
+fn entry_metadata(entry: &Value) -> Option<Metadata> {
+    let url = entry.get("url").and_then(Value::as_str)?;
+
+    // Older youtube-dl versions only give the video id for YouTube entries
+    let source_url = if url.starts_with("http") {
+        url.to_string()
+    } else if entry.get("ie_key").and_then(Value::as_str) == Some("Youtube") {
+        format!("https://www.youtube.com/watch?v={}", url)
+    } else {
+        return None;
+    };
+
+    let get_str = |key| entry.get(key).and_then(Value::as_str).map(str::to_string);
+
+    Some(Metadata {
+        title: get_str("title"),
+        channel: get_str("channel").or_else(|| get_str("uploader")),
+        duration: entry
+            .get("duration")
+            .and_then(Value::as_f64)
+            .map(Duration::from_secs_f64),
+        source_url: Some(source_url),
+        channels: Some(2),
+        ..Metadata::default()
+    })
+}
+
+/// Lists the first `end` entries behind `url` (a playlist or search) using
+/// youtube-dl's flat playlist output, which doesn't resolve the entries themselves.
+pub(super) async fn list_flat(url: &str, end: usize) -> SunnyResult<Vec<Metadata>> {
+    let end = end.to_string();
+
+    let output = Command::new(YOUTUBE_DL)
+        .args([
+            "-J",
+            "--flat-playlist",
+            "--playlist-end",
+            &end,
+            "--ignore-config",
+            "--no-warnings",
+            url,
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| SunnyError::log(format!("Failed to run youtube-dl: {}", e).as_str()))?;
+
+    if !output.status.success() {
+        return Err(SunnyError::user_and_log(
+            "Couldn't look that up",
+            String::from_utf8_lossy(&output.stderr).as_ref(),
+        ));
+    }
+
+    let value: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| SunnyError::log(format!("Failed to parse playlist json: {}", e).as_str()))?;
+
+    Ok(value
+        .get("entries")
+        .and_then(Value::as_array)
+        .map(|entries| entries.iter().filter_map(entry_metadata).collect())
+        .unwrap_or_default())
+}
+
+/// Lists up to `limit` entries of a playlist without resolving the entries themselves
+#[instrument]
+pub async fn list_playlist(url: &str, limit: usize) -> SunnyResult<Playlist> {
+    // Ask for one more entry than needed, to see if the playlist got cut off
+    let mut entries = list_flat(url, limit + 1).await?;
+
+    let truncated = entries.len() > limit;
+    entries.truncate(limit);
+
+    if entries.is_empty() {
+        return Err(SunnyError::user("That playlist is empty"));
+    }
+
+    Ok(Playlist { entries, truncated })
+}
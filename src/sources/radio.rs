@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    os::unix::io::OwnedFd,
+    process::{Command, Stdio},
+    sync::Mutex,
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serenity::{async_trait, model::id::GuildId};
+use songbird::input::{
+    error::{Error, Result},
+    restartable::Restart,
+    Codec, Container, Input, Metadata, Restartable,
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::instrument;
+use url::Url;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::filter::get_filter;
+
+/// Marks urls of live streams, e.g. `radio:https://example.com/stream.mp3`
+pub const RADIO_PREFIX: &str = "radio:";
+
+/// What a live stream announces about itself through its ICY metadata
+#[derive(Clone, Debug, Default)]
+pub struct StreamInfo {
+    /// Name of the station
+    pub station: Option<String>,
+    /// What's playing right now, usually `artist - title`
+    pub title: Option<String>,
+}
+
+/// The ffmpeg currently streaming a station to a guild, along with what it said last
+struct Stream {
+    /// Process id of the ffmpeg, which changes with every restart
+    pid: u32,
+    info: StreamInfo,
+}
+
+/// The latest info of every stream which is playing, by guild and stream url.
+///
+/// Two guilds may tune in to the same station, each through its own ffmpeg.
+/// Only ever locked briefly and from sync code too, so this isn't an async lock.
+static STREAMS: Lazy<Mutex<HashMap<(GuildId, String), Stream>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Gets the value of `key` from ffmpeg log lines like `StreamTitle : value` and
+/// `Metadata update for StreamTitle: value`
+fn log_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (_, rest) = line.split_once(key)?;
+    let (_, value) = rest.split_once(':')?;
+
+    Some(value.trim()).filter(|v| !v.is_empty())
+}
+
+/// Follows the log of the ffmpeg with `pid` for ICY metadata, until it exits
+async fn read_log(key: (GuildId, String), pid: u32, stderr: File) {
+    let mut lines = BufReader::new(tokio::fs::File::from_std(stderr)).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let station = log_value(&line, "icy-name");
+        let title = log_value(&line, "StreamTitle");
+
+        if station.is_none() && title.is_none() {
+            continue;
+        }
+
+        if let Ok(mut streams) = STREAMS.lock() {
+            let stream = streams.entry(key.clone()).or_insert(Stream {
+                pid,
+                info: StreamInfo::default(),
+            });
+
+            // A restart replaced the stream, what the old one said is outdated
+            if stream.pid != pid {
+                stream.pid = pid;
+                stream.info = StreamInfo::default();
+            }
+
+            if let Some(station) = station {
+                stream.info.station = Some(station.to_string());
+            }
+
+            if let Some(title) = title {
+                stream.info.title = Some(title.to_string());
+            }
+        }
+    }
+
+    // The stream stopped, so whatever it said last is outdated.
+    // A restart may have started reporting already, that info is kept.
+    if let Ok(mut streams) = STREAMS.lock() {
+        if streams.get(&key).map(|s| s.pid) == Some(pid) {
+            streams.remove(&key);
+        }
+    }
+}
+
+/// Gets what the live stream of `source_url` is playing in `guild_id`, if it is one
+pub fn stream_info(guild_id: GuildId, source_url: &str) -> Option<StreamInfo> {
+    let url = source_url.strip_prefix(RADIO_PREFIX)?;
+
+    Some(
+        STREAMS
+            .lock()
+            .ok()
+            .and_then(|streams| {
+                streams
+                    .get(&(guild_id, url.to_string()))
+                    .map(|s| s.info.clone())
+            })
+            .unwrap_or_default(),
+    )
+}
+
+/// Streams an http radio station through ffmpeg, which also reports its ICY metadata
+struct RadioRestarter {
+    url: String,
+    /// Whose filter is applied
+    guild_id: GuildId,
+    metadata: Metadata,
+}
+
+#[async_trait]
+impl Restart for RadioRestarter {
+    /// Live streams can't seek, they always start at whatever is on now
+    async fn call_restart(&mut self, _time: Option<Duration>) -> Result<Input> {
+        let filter_args = get_filter(self.guild_id).await.args();
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostats"])
+            .args(["-reconnect", "1", "-reconnect_streamed", "1"])
+            .args(["-icy", "1", "-i", &self.url])
+            .args(&filter_args)
+            .args([
+                "-f",
+                "s16le",
+                "-ac",
+                "2",
+                "-ar",
+                "48000",
+                "-acodec",
+                "pcm_f32le",
+                "-",
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stderr = ffmpeg.stderr.take().ok_or(Error::Stdout)?;
+        let stderr = File::from(OwnedFd::from(stderr));
+        let key = (self.guild_id, self.url.clone());
+        tokio::spawn(read_log(key, ffmpeg.id(), stderr));
+
+        Ok(Input::new(
+            true,
+            ffmpeg.into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            Some(self.metadata.clone()),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)> {
+        Ok((Some(self.metadata.clone()), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// Creates a lazy source for the live stream at `uri`, an http url prefixed
+/// with [`RADIO_PREFIX`]
+#[instrument]
+pub async fn radio(uri: String, guild_id: GuildId) -> SunnyResult<Restartable> {
+    let invalid = || SunnyError::user("Give me the http url of a radio stream");
+
+    let url = uri.strip_prefix(RADIO_PREFIX).ok_or_else(invalid)?;
+
+    // ffmpeg opens all sorts of things, only let it near the web
+    let parsed = Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(invalid)?;
+
+    let restarter = RadioRestarter {
+        url: url.to_string(),
+        guild_id,
+        metadata: Metadata {
+            title: parsed.host_str().map(str::to_string),
+            channel: Some("Live radio".to_string()),
+            source_url: Some(uri.clone()),
+            channels: Some(2),
+            ..Metadata::default()
+        },
+    };
+
+    Restartable::new(restarter, true).await.map_err(|e| {
+        SunnyError::user_and_log(
+            "Couldn't tune in to that station",
+            format!("Error sourcing ffmpeg {:?}", e).as_str(),
+        )
+    })
+}
@@ -0,0 +1,42 @@
+use songbird::input::Metadata;
+use tracing::instrument;
+use url::Url;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::playlist::list_flat;
+
+/// Amount of entries of a mix looked at, the first ones are the most related
+const MIX_ENTRIES: usize = 25;
+
+/// Gets the id of the YouTube video behind `url`, if it is one
+fn video_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+
+    match url.host_str()? {
+        "youtube.com" | "www.youtube.com" | "m.youtube.com" | "music.youtube.com" => url
+            .query_pairs()
+            .find(|(k, _)| k == "v")
+            .map(|(_, v)| v.into_owned()),
+        "youtu.be" => url.path_segments()?.next().map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Lists songs related to the one behind `url`, most related first.
+///
+/// Uses the mix YouTube generates for every video, so this only works for YouTube.
+#[instrument]
+pub async fn related(url: &str) -> SunnyResult<Vec<Metadata>> {
+    let id = video_id(url)
+        .ok_or_else(|| SunnyError::user("Can only find songs related to YouTube videos"))?;
+
+    let mix = format!("https://www.youtube.com/watch?v={}&list=RD{}", id, id);
+    let entries = list_flat(&mix, MIX_ENTRIES).await?;
+
+    // A mix starts with the video it's made from
+    Ok(entries
+        .into_iter()
+        .filter(|m| m.source_url.as_deref().and_then(video_id).as_deref() != Some(id.as_str()))
+        .collect())
+}
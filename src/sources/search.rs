@@ -0,0 +1,25 @@
+use songbird::input::Metadata;
+use tracing::instrument;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::playlist::list_flat;
+
+/// Turns search terms into something youtube-dl plays the first YouTube result of
+pub fn first_result(terms: &str) -> String {
+    format!("ytsearch1:{}", terms)
+}
+
+/// Looks up the top `amount` YouTube results for `terms`
+#[instrument]
+pub async fn search(terms: &str, amount: usize) -> SunnyResult<Vec<Metadata>> {
+    let results = list_flat(&format!("ytsearch{}:{}", amount, terms), amount).await?;
+
+    if results.is_empty() {
+        return Err(SunnyError::user(
+            "Couldn't find anything :person_shrugging:",
+        ));
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use serenity::{async_trait, model::id::GuildId, prelude::Mutex};
+use songbird::input::{
+    error::{Error, Result},
+    restartable::Restart,
+    Codec, Container, Input, Metadata, Restartable,
+};
+use tokio::process::Command as TokioCommand;
+use url::Url;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+use super::{
+    filter::{get_filter, Timeline},
+    YOUTUBE_DL,
+};
+
+/// The youtube-dl format picked for streaming, the best audio there is
+const AUDIO_FORMAT: &str = "webm[abr>0]/bestaudio/best";
+/// How long stream urls without an expiry date are assumed to work
+const ASSUMED_LIFETIME: Duration = Duration::from_secs(30 * 60);
+/// How long before it expires a stream url is resolved again, so it doesn't
+/// run out in the middle of a song
+const EXPIRY_MARGIN: Duration = Duration::from_secs(15 * 60);
+
+/// Arguments ffmpeg converts the audio it reads with
+const FFMPEG_OUTPUT_ARGS: [&str; 9] = [
+    "-f",
+    "s16le",
+    "-ac",
+    "2",
+    "-ar",
+    "48000",
+    "-acodec",
+    "pcm_f32le",
+    "-",
+];
+
+/// Recreates a youtube-dl stream, like songbird's own ytdl restarter, but
+/// skips the metadata lookup when the metadata is already known and streams
+/// prefetched urls without youtube-dl.
+struct YtdlRestarter {
+    uri: String,
+    /// Whose filter is applied
+    guild_id: GuildId,
+    /// Where in the song the track is
+    timeline: Timeline,
+    metadata: Option<Metadata>,
+}
+
+/// A stream youtube-dl resolved, which can be played without asking it again
+/// until the url expires
+#[derive(Clone, Debug)]
+struct Stream {
+    url: String,
+    /// The http headers the url has to be requested with
+    headers: String,
+    metadata: Metadata,
+    expires_at: SystemTime,
+}
+
+impl Stream {
+    fn from_ytdl_output(value: Value) -> Option<Self> {
+        let url = value.get("url")?.as_str()?.to_string();
+
+        let headers = value
+            .get("http_headers")
+            .and_then(Value::as_object)
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|(k, v)| Some(format!("{}: {}\r\n", k, v.as_str()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // YouTube says when its urls expire, other sites are given a guess
+        let expires_at = Url::parse(&url)
+            .ok()
+            .and_then(|u| {
+                u.query_pairs()
+                    .find(|(k, _)| k == "expire")
+                    .and_then(|(_, v)| v.parse().ok())
+            })
+            .map_or_else(
+                || SystemTime::now() + ASSUMED_LIFETIME,
+                |secs| UNIX_EPOCH + Duration::from_secs(secs),
+            );
+
+        Some(Self {
+            url,
+            headers,
+            metadata: Metadata::from_ytdl_output(value),
+            expires_at,
+        })
+    }
+
+    /// Whether the url still works for long enough to start playing it
+    fn is_fresh(&self) -> bool {
+        SystemTime::now() + EXPIRY_MARGIN < self.expires_at
+    }
+}
+
+/// Streams which were resolved already, by the uri they were resolved for
+static STREAMS: Lazy<Mutex<HashMap<String, Stream>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Uris youtube-dl is being asked about right now, locked while it is
+static RESOLVING: Lazy<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Gets the resolved stream of `uri`, unless it's about to expire
+async fn cached_stream(uri: &str) -> Option<Stream> {
+    STREAMS
+        .lock()
+        .await
+        .get(uri)
+        .filter(|s| s.is_fresh())
+        .cloned()
+}
+
+/// Asks youtube-dl for the stream url and metadata of `uri` and caches them
+async fn resolve(uri: &str) -> Result<Stream> {
+    let output = TokioCommand::new(YOUTUBE_DL)
+        .args([
+            "-j",
+            "-f",
+            AUDIO_FORMAT,
+            "--no-playlist",
+            "--ignore-config",
+            "--no-warnings",
+            uri,
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(Error::YouTubeDlRun(output));
+    }
+
+    let value = serde_json::from_slice(&output.stdout).map_err(|error| Error::Json {
+        error,
+        parsed_text: String::from_utf8_lossy(&output.stdout).to_string(),
+    })?;
+
+    let stream = Stream::from_ytdl_output(value).ok_or(Error::Metadata)?;
+
+    let mut streams = STREAMS.lock().await;
+    streams.retain(|_, s| s.is_fresh());
+    streams.insert(uri.to_string(), stream.clone());
+
+    // Searches are played by the url of what they found later on
+    if let Some(url) = &stream.metadata.source_url {
+        streams.insert(url.clone(), stream.clone());
+    }
+
+    Ok(stream)
+}
+
+/// Gets the resolved stream of `uri`, resolving it again if it expired.
+///
+/// Only one task asks youtube-dl about a uri at a time, the others wait for it
+/// and take what it cached.
+async fn get_stream(uri: &str) -> Result<Stream> {
+    if let Some(stream) = cached_stream(uri).await {
+        return Ok(stream);
+    }
+
+    let lock = RESOLVING
+        .lock()
+        .await
+        .entry(uri.to_string())
+        .or_default()
+        .clone();
+
+    let guard = lock.lock().await;
+
+    let stream = match cached_stream(uri).await {
+        Some(stream) => Ok(stream),
+        None => resolve(uri).await,
+    };
+
+    drop(guard);
+
+    // Nobody else is waiting once only the map and this task hold the lock
+    let mut resolving = RESOLVING.lock().await;
+    if Arc::strong_count(&lock) <= 2 {
+        resolving.remove(uri);
+    }
+
+    stream
+}
+
+/// Resolves `uri` ahead of time, so it starts playing without waiting for youtube-dl
+pub(super) async fn prefetch(uri: &str) -> SunnyResult<()> {
+    get_stream(uri)
+        .await
+        .map(|_| ())
+        .map_err(|e| SunnyError::log(format!("Failed to resolve {}: {:?}", uri, e).as_str()))
+}
+
+/// Gets the url the audio of `uri` is streamed from
+pub(super) async fn stream_url(uri: &str) -> SunnyResult<String> {
+    get_stream(uri)
+        .await
+        .map(|s| s.url)
+        .map_err(|e| SunnyError::log(format!("Failed to resolve {}: {:?}", uri, e).as_str()))
+}
+
+#[async_trait]
+impl Restart for YtdlRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> Result<Input> {
+        let filter = get_filter(self.guild_id).await;
+
+        let seek_args = time
+            .map(|t| self.timeline.restart(t, filter))
+            .map(|t| vec!["-ss".to_string(), format!("{:.3}", t.as_secs_f64())])
+            .unwrap_or_default();
+
+        let filter_args = filter.args();
+
+        // A prefetched stream is read by ffmpeg directly, which also seeks without
+        // downloading everything before the position
+        if let Some(stream) = cached_stream(&self.uri).await {
+            let header_args = if stream.headers.is_empty() {
+                vec![]
+            } else {
+                vec!["-headers", stream.headers.as_str()]
+            };
+
+            let ffmpeg = Command::new("ffmpeg")
+                .args(["-reconnect", "1", "-reconnect_streamed", "1"])
+                .args(["-reconnect_delay_max", "5"])
+                .args(&header_args)
+                .args(&seek_args)
+                .args(["-i", &stream.url])
+                .args(&filter_args)
+                .args(FFMPEG_OUTPUT_ARGS)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()?;
+
+            return Ok(Input::new(
+                true,
+                ffmpeg.into(),
+                Codec::FloatPcm,
+                Container::Raw,
+                self.metadata.clone(),
+            ));
+        }
+
+        let ytdl_args = [
+            "-f",
+            AUDIO_FORMAT,
+            "-R",
+            "infinite",
+            "--no-playlist",
+            "--ignore-config",
+            "--no-warnings",
+            &self.uri,
+            "-o",
+            "-",
+        ];
+
+        let mut youtube_dl = Command::new(YOUTUBE_DL)
+            .args(ytdl_args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let taken_stdout = youtube_dl.stdout.take().ok_or(Error::Stdout)?;
+
+        let ffmpeg = Command::new("ffmpeg")
+            .args(&seek_args)
+            .args(["-i", "-"])
+            .args(&filter_args)
+            .args(FFMPEG_OUTPUT_ARGS)
+            .stdin(taken_stdout)
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        Ok(Input::new(
+            true,
+            vec![youtube_dl, ffmpeg].into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            self.metadata.clone(),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)> {
+        if self.metadata.is_none() {
+            let metadata = get_stream(&self.uri).await?.metadata;
+
+            // Stick to the found video, a search might turn up something else later on
+            if let Some(url) = &metadata.source_url {
+                self.uri = url.clone();
+            }
+
+            self.metadata = Some(metadata);
+        }
+
+        Ok((self.metadata.clone(), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// Creates a lazy youtube-dl source for `uri`, played with the filter of `guild_id`.
+///
+/// Without `metadata` youtube-dl is asked for it up front, otherwise nothing
+/// runs until the track is about to play.
+pub async fn ytdl(
+    uri: String,
+    metadata: Option<Metadata>,
+    guild_id: GuildId,
+    timeline: Timeline,
+) -> SunnyResult<Restartable> {
+    let restarter = YtdlRestarter {
+        uri,
+        guild_id,
+        timeline,
+        metadata,
+    };
+
+    Restartable::new(restarter, true).await.map_err(|e| {
+        SunnyError::user_and_log(
+            "Error starting stream",
+            format!("Error sourcing ffmpeg {:?}", e).as_str(),
+        )
+    })
+}
@@ -0,0 +1,112 @@
+//! # Storage
+//! A small JSON file store for anything that should survive a restart.
+//! Every path is relative to the `DATA_DIR` environment variable, `data` by default.
+
+use std::{
+    env,
+    ffi::OsStr,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use serenity::prelude::Mutex;
+use tokio::fs;
+use tracing::instrument;
+
+use crate::utils::{SunnyError, SunnyResult};
+
+static DATA_DIR: Lazy<PathBuf> =
+    Lazy::new(|| env::var("DATA_DIR").map_or_else(|_| PathBuf::from("data"), PathBuf::from));
+
+/// Writes go through a temporary file, which concurrent writers would trample
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> SunnyError {
+    SunnyError::log(format!("Failed to {} {}: {}", action, path.display(), e).as_str())
+}
+
+/// Reads the value stored at `path`, if there is one
+#[instrument]
+pub async fn load<T: DeserializeOwned>(path: &Path) -> SunnyResult<Option<T>> {
+    let path = DATA_DIR.join(path);
+
+    let bytes = match fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error("read", &path, e)),
+    };
+
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| SunnyError::log(format!("Failed to parse {}: {}", path.display(), e).as_str()))
+}
+
+/// Stores `value` at `path`, replacing whatever was there.
+///
+/// The value is written to a temporary file first, so a crash halfway never
+/// leaves a corrupt file behind.
+#[instrument(skip(value))]
+pub async fn save<T: Serialize>(path: &Path, value: &T) -> SunnyResult<()> {
+    let path = DATA_DIR.join(path);
+    let tmp = path.with_extension("tmp");
+
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| SunnyError::log(format!("Failed to serialize: {}", e).as_str()))?;
+
+    let _guard = WRITE_LOCK.lock().await;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .await
+            .map_err(|e| io_error("create", dir, e))?;
+    }
+
+    fs::write(&tmp, json)
+        .await
+        .map_err(|e| io_error("write", &tmp, e))?;
+
+    fs::rename(&tmp, &path)
+        .await
+        .map_err(|e| io_error("replace", &path, e))
+}
+
+/// Removes the value stored at `path`, doing nothing if there is none
+#[instrument]
+pub async fn remove(path: &Path) -> SunnyResult<()> {
+    let path = DATA_DIR.join(path);
+
+    match fs::remove_file(&path).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error("remove", &path, e)),
+        _ => Ok(()),
+    }
+}
+
+/// Lists the paths of all values stored in `dir`
+#[instrument]
+pub async fn list(dir: &Path) -> SunnyResult<Vec<PathBuf>> {
+    let full_dir = DATA_DIR.join(dir);
+
+    let mut entries = match fs::read_dir(&full_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error("read", &full_dir, e)),
+    };
+
+    let mut paths = Vec::new();
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| io_error("read", &full_dir, e))?
+    {
+        let name = PathBuf::from(entry.file_name());
+
+        if name.extension() == Some(OsStr::new("json")) {
+            paths.push(dir.join(name));
+        }
+    }
+
+    Ok(paths)
+}
@@ -1,9 +1,15 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serenity::{
     client::Context,
-    model::id::{ChannelId, GuildId},
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::TypeMapKey,
 };
+use songbird::{input::Metadata, tracks::TrackHandle};
+
+use crate::sources::Timeline;
 
 #[derive(Clone)]
 pub struct EventConfig {
@@ -22,3 +28,94 @@ impl Debug for EventConfig {
             .finish()
     }
 }
+
+/// Who queued a track and when, kept in the typemap of its [`TrackHandle`]
+#[derive(Clone, Copy, Debug)]
+pub struct Requester {
+    pub user_id: UserId,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+impl TypeMapKey for Requester {
+    type Value = Self;
+}
+
+/// Marks tracks Sunny picked herself because the queue ran out
+#[derive(Clone, Copy, Debug)]
+pub struct Autoplayed;
+
+impl TypeMapKey for Autoplayed {
+    type Value = Self;
+}
+
+/// Factor the volume of a normalized track is multiplied with, see [`crate::effects::loudness`]
+pub struct TrackGain;
+
+impl TypeMapKey for TrackGain {
+    type Value = f32;
+}
+
+/// Where in its song a track is, see [`Timeline`]
+pub struct TrackTimeline;
+
+impl TypeMapKey for TrackTimeline {
+    type Value = Timeline;
+}
+
+/// The parts of a track's [`Metadata`] worth keeping around, e.g. to rebuild a queue
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedTrack {
+    pub source_url: String,
+    #[serde(default)]
+    pub requester: Option<UserId>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub channel: Option<String>,
+    pub duration: Option<Duration>,
+    pub thumbnail: Option<String>,
+}
+
+impl SavedTrack {
+    /// Tracks without a source url can't be played again, so they can't be saved either
+    pub fn from_metadata(m: &Metadata) -> Option<Self> {
+        Some(Self {
+            source_url: m.source_url.clone()?,
+            requester: None,
+            title: m.track.clone().or_else(|| m.title.clone()),
+            artist: m.artist.clone(),
+            channel: m.channel.clone(),
+            duration: m.duration,
+            thumbnail: m.thumbnail.clone(),
+        })
+    }
+
+    /// Like [`Self::from_metadata`], but also keeps the [`Requester`]
+    pub async fn from_track(track: &TrackHandle) -> Option<Self> {
+        let requester = track
+            .typemap()
+            .read()
+            .await
+            .get::<Requester>()
+            .map(|r| r.user_id);
+
+        Some(Self {
+            requester,
+            ..Self::from_metadata(track.metadata())?
+        })
+    }
+}
+
+impl From<SavedTrack> for Metadata {
+    fn from(t: SavedTrack) -> Self {
+        Self {
+            title: t.title,
+            artist: t.artist,
+            channel: t.channel,
+            duration: t.duration,
+            source_url: Some(t.source_url),
+            thumbnail: t.thumbnail,
+            channels: Some(2),
+            ..Self::default()
+        }
+    }
+}
@@ -31,7 +31,7 @@ impl SunnyError {
 #[macro_export]
 macro_rules! sunny_log {
     ($err:expr, $ctx:expr, $msg:expr, $lvl:expr) => {
-        use crate::emit;
+        use $crate::emit;
 
         let error: &SunnyError = $err;
         let ctx: &Context = $ctx;